@@ -0,0 +1,25 @@
+//! Searches a fixed set of positions to a fixed depth and prints the nodes and speed
+//!
+//! ```text
+//! bench [depth]
+//! ```
+//!
+//! The node count is the same on every run and every machine, so comparing it before and
+//! after a change shows whether the change altered the search
+
+use anyhow::{Context, Result};
+use engine::search::bench::{bench, BENCH_DEPTH, BENCH_POSITIONS};
+
+fn main() -> Result<()> {
+    let depth = match std::env::args().nth(1) {
+        Some(depth) => depth.parse().context("usage: bench [depth]")?,
+        None => BENCH_DEPTH,
+    };
+
+    let result = bench(depth)?;
+    for (i, (fen, nodes)) in BENCH_POSITIONS.iter().zip(&result.nodes).enumerate() {
+        println!("position {}: {nodes} nodes, {fen}", i + 1);
+    }
+    println!("{result}");
+    Ok(())
+}
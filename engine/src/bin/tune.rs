@@ -0,0 +1,74 @@
+//! Tunes the evaluation weights on a file of labelled positions
+//!
+//! ```text
+//! tune <positions> [-o <output>] [-p <params>] [-i <iterations>]
+//! ```
+
+use anyhow::{bail, Context, Result};
+use engine::{eval::Params, tune};
+use std::sync::Arc;
+
+struct Args {
+    positions: String,
+    output: String,
+    params: Option<String>,
+    iterations: usize,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut args = std::env::args().skip(1);
+    let mut positions = None;
+    let mut output = "params.txt".to_string();
+    let mut params = None;
+    let mut iterations = 100;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "-o" => output = value()?,
+            "-p" => params = Some(value()?),
+            "-i" => iterations = value()?.parse().context("invalid number of iterations")?,
+            _ if arg.starts_with('-') => bail!("unknown option {arg}"),
+            _ => positions = Some(arg),
+        }
+    }
+
+    Ok(Args {
+        positions: positions
+            .context("usage: tune <positions> [-o <output>] [-p <params>] [-i <iterations>]")?,
+        output,
+        params,
+        iterations,
+    })
+}
+
+fn main() -> Result<()> {
+    let args = parse_args()?;
+
+    let params = match &args.params {
+        Some(path) => Params::load(path)?,
+        None => Params::default(),
+    };
+    let mut entries = tune::load(&args.positions)?;
+    println!("loaded {} positions", entries.len());
+
+    let k = tune::fit_k(&mut entries, &Arc::new(params.clone()));
+    println!("k = {k:.4}");
+
+    let tuned = tune::tune(
+        &mut entries,
+        &params,
+        k,
+        args.iterations,
+        |iteration, error, params| {
+            println!("iteration {iteration}: error {error:.6}");
+            // save as we go so a long run can be stopped early
+            if let Err(e) = params.save(&args.output) {
+                eprintln!("{e:#}");
+            }
+        },
+    );
+    tuned.save(&args.output)?;
+    println!("saved to {}", args.output);
+    Ok(())
+}
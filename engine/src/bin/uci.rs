@@ -0,0 +1,12 @@
+//! Speaks the Universal Chess Interface over stdin and stdout, for GUIs and match tools
+//!
+//! ```text
+//! uci
+//! ```
+
+use anyhow::Result;
+use engine::protocol::uci::Uci;
+
+fn main() -> Result<()> {
+    Uci::new(std::io::stdout()).run(std::io::stdin().lock())
+}
@@ -0,0 +1,12 @@
+//! Speaks the Chess Engine Communication Protocol over stdin and stdout, for XBoard and friends
+//!
+//! ```text
+//! xboard
+//! ```
+
+use anyhow::Result;
+use engine::protocol::cecp::Cecp;
+
+fn main() -> Result<()> {
+    Cecp::new(std::io::stdout()).run(std::io::stdin().lock())
+}
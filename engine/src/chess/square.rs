@@ -1,4 +1,6 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Context};
 
 use crate::*;
 
@@ -14,6 +16,17 @@ impl Display for Square {
     }
 }
 
+impl FromStr for Square {
+    type Err = anyhow::Error;
+
+    /// Reads a square like `e4`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let &[file, rank] = s.as_bytes() else { bail!("invalid square {s}") };
+        let (x, y) = (file.wrapping_sub(b'a'), rank.wrapping_sub(b'1'));
+        Square::from_xy(x, y).with_context(|| format!("invalid square {s}"))
+    }
+}
+
 impl Square {
     pub fn from_xy<T: TryInto<u8>>(x: T, y: T) -> Option<Square> {
         let (x, y) = (x.try_into().ok()?, y.try_into().ok()?);
@@ -0,0 +1,223 @@
+//! Extended Position Description, a position and what's known about it
+//!
+//! Test suites like WAC or STS are files of EPD lines, each with the best move or the mate
+//! the engine should find
+
+pub mod suite;
+
+use std::{path::Path, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{rules::Rules, state::State};
+
+/// A position with the operations describing it, like `bm Nf3; id "test 1";`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Epd {
+    /// The position as a FEN, with the move counters taken from the operations or defaulted
+    pub fen: String,
+    /// The opcodes and their operands, in the order they're written
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl FromStr for Epd {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut rest = s.trim();
+        let mut fields = Vec::new();
+        for _ in 0..4 {
+            let (field, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            if field.is_empty() {
+                bail!("EPD needs the board, turn, castling and en passant fields");
+            }
+            fields.push(field);
+            rest = after.trim_start();
+        }
+
+        // some suites are written with the move counters of a FEN as well
+        let mut counters = Vec::new();
+        while counters.len() < 2 {
+            let (field, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            if field.is_empty() || !field.bytes().all(|b| b.is_ascii_digit()) {
+                break;
+            }
+            counters.push(field);
+            rest = after.trim_start();
+        }
+
+        let operations = parse_operations(rest)?;
+        let mut epd = Self {
+            fen: String::new(),
+            operations,
+        };
+        let halfmove = counters
+            .first()
+            .copied()
+            .or(epd.operand("hmvc"))
+            .unwrap_or("0");
+        let fullmove = counters
+            .get(1)
+            .copied()
+            .or(epd.operand("fmvn"))
+            .unwrap_or("1");
+        epd.fen = format!("{} {halfmove} {fullmove}", fields.join(" "));
+        Ok(epd)
+    }
+}
+
+/// Splits `bm Nf3 Nc3; id "a; b";` into opcodes and their operands
+fn parse_operations(text: &str) -> Result<Vec<(String, Vec<String>)>> {
+    let mut operations = Vec::new();
+    let mut chars = text.chars().peekable();
+    loop {
+        while let Some(ch) = chars.peek()
+            && (ch.is_whitespace() || *ch == ';')
+        {
+            chars.next();
+        }
+        let mut opcode = String::new();
+        while let Some(&ch) = chars.peek()
+            && !ch.is_whitespace()
+            && ch != ';'
+        {
+            opcode.push(ch);
+            chars.next();
+        }
+        if opcode.is_empty() {
+            break;
+        }
+
+        let mut operands = Vec::new();
+        loop {
+            match chars.next() {
+                None => bail!("operation {opcode} doesn't end with ;"),
+                Some(';') => break,
+                Some(ch) if ch.is_whitespace() => {}
+                Some('"') => {
+                    let mut operand = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(ch) => operand.push(ch),
+                            None => bail!("unterminated string in operation {opcode}"),
+                        }
+                    }
+                    operands.push(operand);
+                }
+                Some(ch) => {
+                    let mut operand = ch.to_string();
+                    while let Some(&ch) = chars.peek()
+                        && !ch.is_whitespace()
+                        && ch != ';'
+                    {
+                        operand.push(ch);
+                        chars.next();
+                    }
+                    operands.push(operand);
+                }
+            }
+        }
+        operations.push((opcode, operands));
+    }
+    Ok(operations)
+}
+
+impl Epd {
+    /// The operands of an operation, if the position has it
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(op, _)| op == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /// The first operand of an operation
+    pub fn operand(&self, opcode: &str) -> Option<&str> {
+        self.operands(opcode)?.first().map(String::as_str)
+    }
+
+    /// The name of the position, like `WAC.001`
+    pub fn id(&self) -> Option<&str> {
+        self.operand("id")
+    }
+
+    /// The best moves, in SAN, any of them solves the position
+    pub fn best_moves(&self) -> Option<&[String]> {
+        self.operands("bm")
+    }
+
+    /// The moves to avoid, in SAN
+    pub fn avoid_moves(&self) -> Option<&[String]> {
+        self.operands("am")
+    }
+
+    /// The side to move can mate in this many moves
+    pub fn direct_mate(&self) -> Result<Option<i32>> {
+        self.operand("dm")
+            .map(|n| n.parse().with_context(|| format!("invalid dm {n}")))
+            .transpose()
+    }
+
+    /// The comment, `c0`
+    pub fn comment(&self) -> Option<&str> {
+        self.operand("c0")
+    }
+
+    pub fn state(&self) -> Result<State> {
+        State::from_FEN(&self.fen, Rules::standard())
+    }
+}
+
+/// Loads the positions of an EPD file, skipping blank lines
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Epd>> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| line.parse().with_context(|| format!("line {}", i + 1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_epd() {
+        let line = r#"r1bqk2r/pppp1ppp/2n2n2/4p3/1bB1P3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - bm Nd5 O-O; am Nxe5; id "test; 1"; c0 "two best moves";"#;
+        let epd = line.parse::<Epd>().unwrap();
+        assert_eq!(
+            epd.fen,
+            "r1bqk2r/pppp1ppp/2n2n2/4p3/1bB1P3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - 0 1"
+        );
+        assert_eq!(epd.best_moves().unwrap(), ["Nd5", "O-O"]);
+        assert_eq!(epd.avoid_moves().unwrap(), ["Nxe5"]);
+        assert_eq!(epd.id(), Some("test; 1"));
+        assert_eq!(epd.comment(), Some("two best moves"));
+        assert_eq!(epd.direct_mate().unwrap(), None);
+        assert!(epd.state().is_ok());
+
+        // move counters written FEN style or as operations
+        let epd = "8/8/8/8/8/8/8/K1k5 b - - 3 40 dm 1;"
+            .parse::<Epd>()
+            .unwrap();
+        assert_eq!(epd.fen, "8/8/8/8/8/8/8/K1k5 b - - 3 40");
+        assert_eq!(epd.direct_mate().unwrap(), Some(1));
+        let epd = "8/8/8/8/8/8/8/K1k5 b - - hmvc 3; fmvn 40;"
+            .parse::<Epd>()
+            .unwrap();
+        assert_eq!(epd.fen, "8/8/8/8/8/8/8/K1k5 b - - 3 40");
+
+        for invalid in [
+            "8/8/8 w",
+            "8/8/8/8/8/8/8/K1k5 b - - bm Kb2",
+            r#"8/8 w - - id "x;"#,
+        ] {
+            assert!(invalid.parse::<Epd>().is_err(), "{invalid}");
+        }
+    }
+}
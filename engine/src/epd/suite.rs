@@ -0,0 +1,240 @@
+use std::{fmt::Display, path::Path};
+
+use anyhow::{bail, Result};
+
+use crate::{
+    move_gen::{moves::Move, notation::UciMove},
+    search::{alphabeta::SquareMove, limits::SearchLimits, Eval, Searcher},
+    state::State,
+};
+
+use super::{load, Epd};
+
+/// How a position of a suite went
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Solved,
+    Failed,
+    /// The position or its operations couldn't be used, like a best move these rules
+    /// don't have
+    Invalid,
+}
+
+/// What the search made of a position
+#[derive(Clone, Debug)]
+pub struct PositionResult {
+    /// The `id` of the position, or its FEN if it hasn't got one
+    pub id: String,
+    pub outcome: Outcome,
+    /// The move the search chose, in SAN
+    pub played: Option<String>,
+    pub eval: Option<Eval>,
+    /// Why the position is invalid
+    pub error: Option<String>,
+}
+
+impl Display for PositionResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = match self.outcome {
+            Outcome::Solved => "solved",
+            Outcome::Failed => "failed",
+            Outcome::Invalid => "invalid",
+        };
+        write!(f, "{}: {outcome}", self.id)?;
+        if let (Some(played), Some(eval)) = (&self.played, self.eval) {
+            write!(f, ", played {played} ({eval})")?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, ", {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The results of running a suite
+#[derive(Clone, Debug, Default)]
+pub struct SuiteReport {
+    pub name: String,
+    pub results: Vec<PositionResult>,
+}
+
+impl SuiteReport {
+    pub fn count(&self, outcome: Outcome) -> usize {
+        self.results.iter().filter(|r| r.outcome == outcome).count()
+    }
+
+    pub fn solved(&self) -> usize {
+        self.count(Outcome::Solved)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(Outcome::Failed)
+    }
+}
+
+impl Display for SuiteReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}/{} solved, {} failed, {} invalid",
+            self.name,
+            self.solved(),
+            self.results.len(),
+            self.failed(),
+            self.count(Outcome::Invalid)
+        )
+    }
+}
+
+/// A named set of positions to test the search on
+#[derive(Clone, Debug, Default)]
+pub struct Suite {
+    pub name: String,
+    pub positions: Vec<Epd>,
+}
+
+impl Suite {
+    /// Loads an EPD file, named after the file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let name = match path.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => path.display().to_string(),
+        };
+        Ok(Self {
+            name,
+            positions: load(path)?,
+        })
+    }
+
+    /// Searches every position with the same limits, calling `progress` after each one
+    ///
+    /// The searcher is cleared before each position, so with a depth or node limit and a
+    /// single thread the results are the same every time
+    pub fn run(
+        &self,
+        searcher: &mut dyn Searcher,
+        limits: &SearchLimits,
+        mut progress: impl FnMut(&PositionResult),
+    ) -> SuiteReport {
+        let mut report = SuiteReport {
+            name: self.name.clone(),
+            results: Vec::new(),
+        };
+        for epd in &self.positions {
+            let id = epd.id().unwrap_or(&epd.fen).to_string();
+            let result = match run_position(epd, searcher, limits) {
+                Ok((outcome, played, eval)) => PositionResult {
+                    id,
+                    outcome,
+                    played: Some(played),
+                    eval: Some(eval),
+                    error: None,
+                },
+                Err(e) => PositionResult {
+                    id,
+                    outcome: Outcome::Invalid,
+                    played: None,
+                    eval: None,
+                    error: Some(format!("{e:#}")),
+                },
+            };
+            progress(&result);
+            report.results.push(result);
+        }
+        report
+    }
+}
+
+/// Finds the moves a list of SAN moves means in the position
+fn resolve(state: &mut State, moves: &[String]) -> Result<Vec<SquareMove>> {
+    moves
+        .iter()
+        .map(|san| {
+            let m = Move::from_san(san, state)?;
+            Ok((state.board_state.square_of(m.piece), m.to))
+        })
+        .collect()
+}
+
+/// Searches a position and checks the move against its `bm`, `am` and `dm` operations
+fn run_position(
+    epd: &Epd,
+    searcher: &mut dyn Searcher,
+    limits: &SearchLimits,
+) -> Result<(Outcome, String, Eval)> {
+    let mut state = epd.state()?;
+    let best = epd
+        .best_moves()
+        .map(|moves| resolve(&mut state, moves))
+        .transpose()?;
+    let avoid = epd
+        .avoid_moves()
+        .map(|moves| resolve(&mut state, moves))
+        .transpose()?;
+    let mate = epd.direct_mate()?;
+    if best.is_none() && avoid.is_none() && mate.is_none() {
+        bail!("nothing to check, there's no bm, am or dm");
+    }
+
+    searcher.clear();
+    let result = searcher.search(&mut state, limits);
+    let Some(&(from, to)) = result.pv.first() else { bail!("there are no legal moves") };
+    let played = UciMove::from((from, to)).resolve(&mut state)?;
+    let eval = result.eval();
+
+    let solved = match &best {
+        Some(best) => best.contains(&(from, to)),
+        None => true,
+    } && match &avoid {
+        Some(avoid) => !avoid.contains(&(from, to)),
+        None => true,
+    } && match (mate, eval) {
+        (Some(n), Eval::Mate(found)) => (1..=n).contains(&found),
+        (Some(_), Eval::Centipawns(_)) => false,
+        (None, _) => true,
+    };
+    let outcome = match solved {
+        true => Outcome::Solved,
+        false => Outcome::Failed,
+    };
+    Ok((outcome, played.to_san(&mut state), eval))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::search::{alphabeta::AlphaBeta, options::SearchOptions};
+
+    const SUITE: [&str; 6] = [
+        r#"6k1/5ppp/8/8/8/8/8/3Q2K1 w - - bm Qd8#; id "back rank";"#,
+        r#"kbK5/pp6/1P6/8/8/8/8/R7 w - - dm 2; id "mate in 2";"#,
+        r#"q3k3/8/8/3N4/8/8/8/4K3 w - - bm Nc7+; am Ke2; id "fork";"#,
+        r#"4k3/8/8/3q4/8/8/3R4/4K3 w - - bm Kf2; id "wrong answer";"#,
+        r#"4k3/8/8/8/8/8/8/4K2R w K - bm O-O; id "castling";"#,
+        r#"4k3/8/8/8/8/8/8/4K2R w K - c0 "nothing to solve";"#,
+    ];
+
+    #[test]
+    fn test_suite() {
+        let suite = Suite {
+            name: "test".to_string(),
+            positions: SUITE.iter().map(|line| line.parse().unwrap()).collect(),
+        };
+        let mut searcher = AlphaBeta::new(SearchOptions {
+            threads: 1,
+            ..Default::default()
+        });
+        let mut seen = 0;
+        let report = suite.run(&mut searcher, &SearchLimits::depth(6), |_| seen += 1);
+        assert_eq!(seen, 6);
+
+        let outcomes = report.results.iter().map(|r| r.outcome).collect::<Vec<_>>();
+        use Outcome::*;
+        assert_eq!(outcomes, [Solved, Solved, Solved, Failed, Invalid, Invalid]);
+        assert_eq!(report.results[0].played.as_deref(), Some("Qd8#"));
+        assert_eq!(report.results[1].eval, Some(Eval::Mate(2)));
+        assert_eq!(report.results[3].played.as_deref(), Some("Rxd5"));
+        assert_eq!(report.to_string(), "test: 3/6 solved, 1 failed, 2 invalid");
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    chess::{square::Square, Team},
+    rules::piece::PieceKind,
+    state::{board_state::GetPiece, State},
+};
+
+use super::{pawns::forward, score::S};
+
+/// Scores how safe the king of each team is
+pub fn king_safety(state: &State) -> [S; 2] {
+    let board = &state.board_state;
+    let mut out = [S::default(); 2];
+
+    for (i, &idx) in board.board().iter().enumerate() {
+        let piece = *idx.get(board.pieces());
+        if piece.kind() != Some(PieceKind::King) {
+            continue;
+        }
+        let team = piece.team().expect("kings have a team");
+        out[team as usize] += king(state, Square(i as u8), team);
+    }
+
+    out
+}
+
+fn king(state: &State, square: Square, team: Team) -> S {
+    let params = &state.params;
+    let enemy = team.switch();
+    let mut score = S::default();
+
+    // count up the enemy attacks on the king and the squares around it,
+    // a few attackers are manageable but it gets bad quickly
+    let mut attacks = 0;
+    for (x, y) in (-1..=1).flat_map(|x| (-1..=1).map(move |y| (x, y))) {
+        let Some(zone) = square.try_move(x, y) else { continue };
+        attacks += state.moves.threat_at(zone, enemy) as i32;
+        attacks += state.moves.sliding_threat_at(zone, enemy).count_ones() as i32;
+    }
+    score += params.king_attack * (attacks * attacks / 4);
+
+    // our pawns in front of the king shield it, enemy pawns in front of it are storming it
+    let f = forward(team);
+    for x in -1..=1 {
+        for distance in 1..=3 {
+            let Some(front) = square.try_move(x, distance * f) else { continue };
+            let piece = front.get_piece(&state.board_state);
+            if piece.kind() != Some(PieceKind::Pawn) {
+                continue;
+            }
+
+            let distance = distance as usize;
+            if piece.team() == Some(enemy) {
+                score += params.storm[distance - 1];
+            } else if distance <= params.shield.len() {
+                score += params.shield[distance - 1];
+            }
+        }
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rules;
+
+    #[test]
+    fn test_pawn_shield() {
+        let shielded =
+            State::from_FEN("4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1", Rules::standard()).unwrap();
+        let exposed =
+            State::from_FEN("4k3/8/8/8/5PPP/8/8/6K1 w - - 0 1", Rules::standard()).unwrap();
+        let shield = shielded.params.shield[0];
+
+        assert_eq!(king_safety(&shielded)[Team::White as usize], shield * 3);
+        assert_eq!(king_safety(&exposed)[Team::White as usize], S::default());
+    }
+}
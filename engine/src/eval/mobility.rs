@@ -0,0 +1,50 @@
+use crate::state::State;
+
+use super::{pawns::PawnEntry, score::S};
+
+/// Scores how many squares each piece can move to, for each team
+///
+/// Squares attacked by enemy pawns don't count, a piece can't really go there
+pub fn mobility(state: &State, pawns: &PawnEntry) -> [S; 2] {
+    let board = &state.board_state;
+    let mut out = [S::default(); 2];
+
+    for &idx in board.board().iter() {
+        let piece = *idx.get(board.pieces());
+        let (Some(kind), Some(team)) = (piece.kind(), piece.team()) else { continue };
+        let weight = state.params.mobility[kind as usize];
+        if weight == S::default() {
+            continue;
+        }
+
+        let unsafe_squares = pawns.attacks[team.switch() as usize];
+        let count = state
+            .moves
+            .filter(idx)
+            .filter(|m| unsafe_squares & 1 << *m.to == 0)
+            .count();
+        out[team as usize] += weight * count as i32;
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        chess::Team,
+        rules::{piece::PieceKind, Rules},
+    };
+
+    #[test]
+    fn test_mobility() {
+        // the knight on d4 has 8 squares, but the pawns on d7 and g6 cover 3 of them
+        let state =
+            State::from_FEN("4k3/3p4/6p1/8/3N4/8/8/4K3 w - - 0 1", Rules::standard()).unwrap();
+        let pawns = PawnEntry::compute(&state.params, &state.board_state, state.pawn_hash);
+        let knight = state.params.mobility[PieceKind::Knight as usize];
+
+        assert_eq!(mobility(&state, &pawns)[Team::White as usize], knight * 5);
+    }
+}
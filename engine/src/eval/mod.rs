@@ -0,0 +1,263 @@
+pub mod king;
+pub mod mobility;
+pub mod nnue;
+pub mod params;
+pub mod pawns;
+pub mod psqt;
+pub mod score;
+pub mod trace;
+
+use strum::IntoEnumIterator;
+
+use crate::{
+    chess::{square::Square, Team},
+    rules::piece::{Piece, PieceKind},
+    state::{board_state::BoardState, State},
+};
+
+pub use self::trace::trace;
+
+use self::{
+    pawns::{PawnEntry, PawnTable},
+    psqt::Table,
+    score::S,
+};
+
+/// A score in centipawns
+pub type Score = i32;
+
+/// How much each kind of piece counts towards the phase of the game
+pub const PHASE_WEIGHTS: [i32; 6] = [0, 2, 1, 1, 4, 0];
+
+/// The weights used to evaluate a position
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Params {
+    /// Material value of each kind of piece
+    pub material: [S; 6],
+    /// Piece-square table for each kind of piece
+    pub psqt: [Table; 6],
+    /// Penalty for a pawn with another pawn of its own team in front of it
+    pub doubled: S,
+    /// Penalty for a pawn with no pawns of its own team on the files next to it
+    pub isolated: S,
+    /// Penalty for a pawn that has been left behind and can't safely advance
+    pub backward: S,
+    /// Bonus for a pawn that is defended by or standing next to another pawn, by rank
+    pub connected: [S; 8],
+    /// Bonus for a pawn with no enemy pawns in front of it, by rank
+    pub passed: [S; 8],
+    /// Bonus for each square a piece can move to, by kind
+    pub mobility: [S; 6],
+    /// Penalty for enemy attacks around the king, grows with the square of the attacks
+    pub king_attack: S,
+    /// Bonus for our pawns in front of the king, by how far in front they are
+    pub shield: [S; 2],
+    /// Penalty for enemy pawns in front of the king, by how far in front they are
+    pub storm: [S; 3],
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        let mut material = [S::default(); 6];
+        let mut psqt = [[S::default(); 64]; 6];
+        for kind in PieceKind::iter() {
+            let info = kind.piece(Team::White).info().expect("every kind has info");
+            material[kind as usize] = S(info.value as Score, info.value as Score);
+            psqt[kind as usize] = psqt::default_table(kind);
+        }
+        Self {
+            material,
+            psqt,
+            doubled: S(-10, -25),
+            isolated: S(-10, -15),
+            backward: S(-8, -10),
+            connected: [
+                S(0, 0),
+                S(5, 0),
+                S(8, 2),
+                S(12, 5),
+                S(20, 12),
+                S(35, 25),
+                S(55, 45),
+                S(0, 0),
+            ],
+            passed: [
+                S(0, 0),
+                S(5, 10),
+                S(5, 15),
+                S(10, 25),
+                S(20, 45),
+                S(35, 75),
+                S(60, 120),
+                S(0, 0),
+            ],
+            mobility: [S(0, 0), S(2, 4), S(4, 4), S(5, 5), S(1, 2), S(0, 0)],
+            king_attack: S(-3, -1),
+            shield: [S(15, 0), S(8, 0)],
+            storm: [S(-20, -5), S(-12, -3), S(-6, 0)],
+        }
+    }
+}
+
+impl Params {
+    /// The material and piece-square score of a piece on a square, from White's perspective
+    #[inline(always)]
+    pub fn piece_score(&self, piece: Piece, square: Square) -> S {
+        let Some(kind) = piece.kind() else { return S::default() };
+        let team = piece.team().expect("pieces with a kind have a team");
+
+        let score =
+            self.material[kind as usize] + self.psqt[kind as usize][psqt::index(square, team)];
+        match team {
+            Team::White => score,
+            Team::Black => -score,
+        }
+    }
+
+    /// Scores every piece on the board from scratch, from White's perspective
+    pub fn score(&self, board: &BoardState) -> S {
+        board
+            .board()
+            .iter()
+            .enumerate()
+            .map(|(i, &idx)| self.piece_score(*idx.get(board.pieces()), Square(i as u8)))
+            .sum()
+    }
+}
+
+/// How much a piece counts towards the phase of the game
+#[inline(always)]
+pub fn piece_phase(piece: Piece) -> i32 {
+    piece.kind().map_or(0, |kind| PHASE_WEIGHTS[kind as usize])
+}
+
+/// Works out the phase of the game from the non-pawn material left on the board,
+/// from [score::MAX_PHASE] at the start down to 0 when only kings and pawns are left
+pub fn phase(board: &BoardState) -> i32 {
+    board.pieces().iter().map(|&p| piece_phase(p)).sum()
+}
+
+/// Evaluates the position from the perspective of the side to move
+///
+/// Uses the network if the state has one, see [State::set_network].
+/// Works everything out from scratch, use an [Evaluator] to cache the slow parts
+pub fn evaluate(state: &State) -> Score {
+    if let Some(nnue) = &state.nnue {
+        return nnue.evaluate(state.turn);
+    }
+    let pawns = PawnEntry::compute(&state.params, &state.board_state, state.pawn_hash);
+    evaluate_with(state, &pawns)
+}
+
+/// The terms that aren't kept up to date as moves are made, for each team
+///
+/// In the same order as the last few [trace::Term]s
+fn terms(state: &State, pawns: &PawnEntry) -> [[S; 2]; 4] {
+    [
+        pawns.structure,
+        pawns::passed(state, pawns),
+        mobility::mobility(state, pawns),
+        king::king_safety(state),
+    ]
+}
+
+fn evaluate_with(state: &State, pawns: &PawnEntry) -> Score {
+    let mut score = state.score;
+    for [black, white] in terms(state, pawns) {
+        score += white - black;
+    }
+
+    let score = score.taper(state.phase);
+    match state.turn {
+        Team::White => score,
+        Team::Black => -score,
+    }
+}
+
+/// Evaluates positions, caching the parts that don't change often
+///
+/// The caches belong to one set of [Params], clear them if the params change
+pub struct Evaluator {
+    pawns: PawnTable,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self {
+            pawns: PawnTable::new(14),
+        }
+    }
+}
+
+impl Evaluator {
+    /// Evaluates the position from the perspective of the side to move
+    pub fn evaluate(&mut self, state: &State) -> Score {
+        if let Some(nnue) = &state.nnue {
+            return nnue.evaluate(state.turn);
+        }
+        let pawns = self.pawns.probe(state);
+        evaluate_with(state, &pawns)
+    }
+
+    /// Forgets everything that has been cached
+    pub fn clear(&mut self) {
+        self.pawns.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        rules::Rules,
+        state::zobrist::{hash, pawn_hash},
+    };
+
+    #[test]
+    fn test_incremental_score() {
+        let mut state = State::from_FEN(
+            "r3k2r/pp3ppp/2n5/3q4/3P4/2B5/PP3PPP/R2QK2R w KQkq - 0 1",
+            Rules::standard(),
+        )
+        .unwrap();
+        let start = state.score;
+        assert_eq!(start, state.params.score(&state.board_state));
+
+        // Qa4 Qxa2 Qxc6+
+        let line = [
+            (Square(3), Square(24)),
+            (Square(35), Square(8)),
+            (Square(24), Square(42)),
+        ];
+        for (from, to) in line {
+            state.make_move(from, to);
+            assert_eq!(state.score, state.params.score(&state.board_state));
+            assert_eq!(state.phase, phase(&state.board_state));
+            assert_eq!(state.pawn_hash, pawn_hash(&state.board_state));
+            assert_eq!(state.hash, hash(&state.board_state, state.turn));
+        }
+        for _ in line {
+            state.unmake_move();
+            assert_eq!(state.score, state.params.score(&state.board_state));
+        }
+        assert_eq!(state.score, start);
+    }
+
+    #[test]
+    fn test_tapered() {
+        let start = State::from_FEN(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            Rules::standard(),
+        )
+        .unwrap();
+        assert_eq!(start.phase, score::MAX_PHASE);
+        assert_eq!(evaluate(&start), 0);
+        assert_eq!(Evaluator::default().evaluate(&start), 0);
+
+        // with only pawns left the king belongs in the center
+        let center = State::from_FEN("4k3/p7/8/8/4K3/8/P7/8 w - - 0 1", Rules::standard()).unwrap();
+        let corner = State::from_FEN("4k3/p7/8/8/8/8/P7/7K w - - 0 1", Rules::standard()).unwrap();
+        assert_eq!(center.phase, 0);
+        assert!(evaluate(&center) > evaluate(&corner));
+    }
+}
@@ -0,0 +1,265 @@
+//! An efficiently updatable neural network, an alternative to the handcrafted evaluation
+//!
+//! The inputs are HalfKP features: for each side, where its king is combined with where
+//! every other piece is. Only a few features change with each move, so the first layer
+//! (the accumulator) is updated as moves are made instead of being recomputed.
+
+pub mod network;
+pub mod simd;
+
+use std::sync::Arc;
+
+use crate::{
+    chess::{square::Square, Team},
+    rules::piece::{Piece, PieceKind},
+    state::board_state::BoardState,
+};
+
+use super::Score;
+
+pub use self::network::Network;
+
+/// The number of HalfKP features, king square * non-king piece * square
+pub const FEATURES: usize = 64 * 10 * 64;
+
+/// Flips squares so each side sees the board from its own side
+#[inline(always)]
+fn orient(square: Square, perspective: Team) -> usize {
+    match perspective {
+        Team::White => *square as usize,
+        Team::Black => *square as usize ^ 56,
+    }
+}
+
+/// The feature for `piece` standing on `square`, as seen by `perspective` with its king on `king`
+///
+/// Kings aren't features themselves, returns `None` for them
+#[inline(always)]
+pub fn feature(perspective: Team, king: Square, piece: Piece, square: Square) -> Option<usize> {
+    let kind = piece.kind()?;
+    if kind == PieceKind::King {
+        return None;
+    }
+    let piece = kind as usize * 2 + (piece.team() != Some(perspective)) as usize;
+    Some((orient(king, perspective) * 10 + piece) * 64 + orient(square, perspective))
+}
+
+/// The first layer of the network for both sides
+#[derive(Clone, Debug)]
+pub struct Accumulator {
+    /// Where each side's king is
+    pub kings: [Square; 2],
+    /// The accumulator for each side
+    pub values: [Vec<i16>; 2],
+}
+
+/// A network along with the accumulators for every position on the way to the current one
+#[derive(Clone, Debug)]
+pub struct Nnue {
+    pub network: Arc<Network>,
+    /// Entries past `len` are kept around so their memory can be reused
+    stack: Vec<Accumulator>,
+    len: usize,
+}
+
+impl Nnue {
+    /// Sets up the network for a position
+    pub fn new(network: Arc<Network>, board: &BoardState) -> Self {
+        let size = network.accumulator;
+        let mut nnue = Self {
+            network,
+            stack: vec![Accumulator {
+                kings: [Square(0); 2],
+                values: [vec![0; size], vec![0; size]],
+            }],
+            len: 1,
+        };
+        nnue.refresh(board);
+        nnue
+    }
+
+    fn top(&mut self) -> &mut Accumulator {
+        &mut self.stack[self.len - 1]
+    }
+
+    /// Recomputes both accumulators from scratch
+    pub fn refresh(&mut self, board: &BoardState) {
+        for team in [Team::Black, Team::White] {
+            self.refresh_side(board, team);
+        }
+    }
+
+    fn refresh_side(&mut self, board: &BoardState, perspective: Team) {
+        let network = self.network.clone();
+        let king = board
+            .board()
+            .iter()
+            .position(|&idx| *idx.get(board.pieces()) == PieceKind::King.piece(perspective))
+            .map_or(Square(0), |i| Square(i as u8));
+
+        let acc = self.top();
+        acc.kings[perspective as usize] = king;
+        let values = &mut acc.values[perspective as usize];
+        values.copy_from_slice(&network.ft_biases);
+        for (i, &idx) in board.board().iter().enumerate() {
+            let piece = *idx.get(board.pieces());
+            if let Some(feature) = feature(perspective, king, piece, Square(i as u8)) {
+                simd::add(values, network.feature(feature));
+            }
+        }
+    }
+
+    /// Updates the accumulators for a move, `board` is the position after it
+    ///
+    /// `removed` and `added` are the pieces that left and arrived on squares
+    pub fn make_move(
+        &mut self,
+        board: &BoardState,
+        removed: &[(Piece, Square)],
+        added: &[(Piece, Square)],
+    ) {
+        // copy the current accumulator to the next entry
+        if self.len == self.stack.len() {
+            self.stack.push(self.stack[self.len - 1].clone());
+        } else {
+            let (done, next) = self.stack.split_at_mut(self.len);
+            next[0].clone_from(&done[self.len - 1]);
+        }
+        self.len += 1;
+
+        let network = self.network.clone();
+        for perspective in [Team::Black, Team::White] {
+            // when a king moves every feature of its side changes
+            let king = PieceKind::King.piece(perspective);
+            if added.iter().any(|&(piece, _)| piece == king) {
+                self.refresh_side(board, perspective);
+                continue;
+            }
+
+            let acc = self.top();
+            let king = acc.kings[perspective as usize];
+            let values = &mut acc.values[perspective as usize];
+            for &(piece, square) in removed {
+                if let Some(feature) = feature(perspective, king, piece, square) {
+                    simd::sub(values, network.feature(feature));
+                }
+            }
+            for &(piece, square) in added {
+                if let Some(feature) = feature(perspective, king, piece, square) {
+                    simd::add(values, network.feature(feature));
+                }
+            }
+        }
+    }
+
+    /// Goes back to the accumulators from before the last move
+    pub fn unmake_move(&mut self) {
+        debug_assert!(self.len > 1, "no move to take back");
+        self.len -= 1;
+    }
+
+    /// The current accumulators
+    pub fn accumulator(&self) -> &Accumulator {
+        &self.stack[self.len - 1]
+    }
+
+    /// Evaluates the position from the perspective of the side to move
+    pub fn evaluate(&self, turn: Team) -> Score {
+        let values = &self.accumulator().values;
+        self.network
+            .output(&values[turn as usize], &values[turn.switch() as usize])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        rules::Rules,
+        state::{zobrist::splitmix, State},
+    };
+
+    /// A network with small made up weights
+    fn network() -> Network {
+        let mut network = Network::zeroed(32, 8);
+        let mut n = 0;
+        let mut random = |range: i64| {
+            n += 1;
+            (splitmix(n) % (2 * range as u64 + 1)) as i64 - range
+        };
+        network
+            .ft_biases
+            .iter_mut()
+            .for_each(|v| *v = random(20) as i16);
+        network
+            .ft_weights
+            .iter_mut()
+            .for_each(|v| *v = random(20) as i16);
+        network
+            .hidden_biases
+            .iter_mut()
+            .for_each(|v| *v = random(500) as i32);
+        network
+            .hidden_weights
+            .iter_mut()
+            .for_each(|v| *v = random(60) as i8);
+        network
+            .output_weights
+            .iter_mut()
+            .for_each(|v| *v = random(60) as i8);
+        network
+    }
+
+    #[test]
+    fn test_accumulator() {
+        let mut state = State::from_FEN(
+            "r3k2r/pp3ppp/2n5/3q4/3P4/2B5/PP3PPP/R2QK2R w KQkq - 0 1",
+            Rules::standard(),
+        )
+        .unwrap();
+        state.set_network(Some(Arc::new(network())));
+        let start = state.nnue.as_ref().unwrap().accumulator().clone();
+
+        // Qa4 Qxa2 Kf1, the last one moves a king
+        let line = [
+            (Square(3), Square(24)),
+            (Square(35), Square(8)),
+            (Square(4), Square(5)),
+        ];
+        for (from, to) in line {
+            state.make_move(from, to);
+            let nnue = state.nnue.as_ref().unwrap();
+            let fresh = Nnue::new(nnue.network.clone(), &state.board_state);
+            assert_eq!(nnue.accumulator().values, fresh.accumulator().values);
+        }
+        for _ in line {
+            state.unmake_move();
+        }
+        assert_eq!(
+            state.nnue.as_ref().unwrap().accumulator().values,
+            start.values
+        );
+        assert_eq!(
+            crate::eval::evaluate(&state),
+            state.nnue.as_ref().unwrap().evaluate(state.turn)
+        );
+        assert_eq!(crate::eval::trace(&state).score(), crate::eval::evaluate(&state));
+    }
+
+    #[test]
+    fn test_load() {
+        let network = network();
+        let loaded = Network::from_bytes(&network.to_bytes()).unwrap();
+        assert_eq!(loaded.ft_weights, network.ft_weights);
+        assert_eq!(loaded.output_weights, network.output_weights);
+
+        let bytes = network.to_bytes();
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Network::from_bytes(b"nonsense").is_err());
+
+        // sizes in the header that would overflow when multiplied
+        let mut bytes = network.to_bytes();
+        bytes[12..20].fill(0xff);
+        assert!(Network::from_bytes(&bytes).is_err());
+    }
+}
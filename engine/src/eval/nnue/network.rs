@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::eval::Score;
+
+use super::{simd, FEATURES};
+
+/// Activations are clipped to `0..=ACTIVATION_MAX`
+pub const ACTIVATION_MAX: i32 = 127;
+/// Hidden layer weights are scaled up by `2^WEIGHT_SHIFT`
+pub const WEIGHT_SHIFT: i32 = 6;
+/// The network output is divided by this to get centipawns
+pub const OUTPUT_DIVISOR: i32 = 16;
+/// The largest layers a network can have, so inference can work on the stack
+pub const MAX_ACCUMULATOR: usize = 1024;
+pub const MAX_HIDDEN: usize = 256;
+
+const MAGIC: &[u8; 4] = b"OXNN";
+const VERSION: u32 = 1;
+
+/// A quantized network, the feature transformer followed by one hidden layer
+///
+/// ```text
+/// features (HalfKP) -> accumulator (i16, one per side) -> hidden (i8) -> output
+/// ```
+#[derive(Clone)]
+pub struct Network {
+    /// The size of the accumulator for one side
+    pub accumulator: usize,
+    /// The size of the hidden layer
+    pub hidden: usize,
+    pub ft_biases: Vec<i16>,
+    /// `accumulator` weights for every feature
+    pub ft_weights: Vec<i16>,
+    pub hidden_biases: Vec<i32>,
+    /// `2 * accumulator` weights for every hidden neuron, side to move first
+    pub hidden_weights: Vec<i8>,
+    pub output_bias: i32,
+    pub output_weights: Vec<i8>,
+}
+
+impl std::fmt::Debug for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Network({FEATURES}x{} -> {} -> 1)",
+            self.accumulator, self.hidden
+        )
+    }
+}
+
+impl Network {
+    /// Creates a network with every weight set to 0
+    ///
+    /// Panics if a layer is bigger than [MAX_ACCUMULATOR] or [MAX_HIDDEN]
+    pub fn zeroed(accumulator: usize, hidden: usize) -> Self {
+        assert!(accumulator <= MAX_ACCUMULATOR && hidden <= MAX_HIDDEN);
+        Self {
+            accumulator,
+            hidden,
+            ft_biases: vec![0; accumulator],
+            ft_weights: vec![0; FEATURES * accumulator],
+            hidden_biases: vec![0; hidden],
+            hidden_weights: vec![0; hidden * 2 * accumulator],
+            output_bias: 0,
+            output_weights: vec![0; hidden],
+        }
+    }
+
+    /// The feature transformer weights for one feature
+    #[inline(always)]
+    pub fn feature(&self, feature: usize) -> &[i16] {
+        &self.ft_weights[feature * self.accumulator..][..self.accumulator]
+    }
+
+    /// Runs the rest of the network on the accumulators of the side to move and the other side
+    pub fn output(&self, us: &[i16], them: &[i16]) -> Score {
+        let mut input = [0; 2 * MAX_ACCUMULATOR];
+        let input = &mut input[..2 * self.accumulator];
+        for (input, &v) in input.iter_mut().zip(us.iter().chain(them)) {
+            *input = (v as i32).clamp(0, ACTIVATION_MAX) as u8;
+        }
+
+        let mut hidden = [0; MAX_HIDDEN];
+        let hidden = &mut hidden[..self.hidden];
+        for (i, neuron) in hidden.iter_mut().enumerate() {
+            let weights = &self.hidden_weights[i * input.len()..][..input.len()];
+            let sum = self.hidden_biases[i] + simd::dot(input, weights);
+            *neuron = (sum >> WEIGHT_SHIFT).clamp(0, ACTIVATION_MAX) as u8;
+        }
+
+        let output = self.output_bias + simd::dot(hidden, &self.output_weights);
+        output / OUTPUT_DIVISOR
+    }
+
+    /// Loads a network written by [Network::save]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).with_context(|| format!("could not read {}", path.display()))?;
+        Self::from_bytes(&bytes).with_context(|| format!("invalid network {}", path.display()))
+    }
+
+    /// Writes the network to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes())
+            .with_context(|| format!("could not write {}", path.display()))
+    }
+
+    /// Parses a network
+    ///
+    /// Everything is little endian: the magic `OXNN`, the version, the accumulator and
+    /// hidden sizes as `u32`s, then every weight in the order of the fields
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC {
+            bail!("not a network file");
+        }
+        let version = reader.u32()?;
+        ensure!(version == VERSION, "unsupported version {version}");
+
+        let accumulator = reader.u32()? as usize;
+        let hidden = reader.u32()? as usize;
+        // check the size before allocating anything, the header could be nonsense
+        let expected = [
+            accumulator.checked_mul(2),
+            FEATURES.checked_mul(accumulator).and_then(|n| n.checked_mul(2)),
+            hidden.checked_mul(4),
+            hidden.checked_mul(2).and_then(|n| n.checked_mul(accumulator)),
+            Some(4),
+            Some(hidden),
+        ]
+        .into_iter()
+        .try_fold(0usize, |total, size| total.checked_add(size?))
+        .with_context(|| format!("layer sizes {accumulator} and {hidden} are too big"))?;
+        ensure!(
+            accumulator <= MAX_ACCUMULATOR && hidden <= MAX_HIDDEN,
+            "layer sizes {accumulator} and {hidden} are bigger than {MAX_ACCUMULATOR} and {MAX_HIDDEN}"
+        );
+        ensure!(
+            reader.0.len() == expected,
+            "expected {expected} bytes of weights, found {}",
+            reader.0.len()
+        );
+
+        let mut network = Self::zeroed(accumulator, hidden);
+        reader.fill(&mut network.ft_biases, i16::from_le_bytes)?;
+        reader.fill(&mut network.ft_weights, i16::from_le_bytes)?;
+        reader.fill(&mut network.hidden_biases, i32::from_le_bytes)?;
+        reader.fill(&mut network.hidden_weights, i8::from_le_bytes)?;
+        network.output_bias = reader.u32()? as i32;
+        reader.fill(&mut network.output_weights, i8::from_le_bytes)?;
+        Ok(network)
+    }
+
+    /// The opposite of [Network::from_bytes]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.extend(VERSION.to_le_bytes());
+        out.extend((self.accumulator as u32).to_le_bytes());
+        out.extend((self.hidden as u32).to_le_bytes());
+        out.extend(self.ft_biases.iter().flat_map(|v| v.to_le_bytes()));
+        out.extend(self.ft_weights.iter().flat_map(|v| v.to_le_bytes()));
+        out.extend(self.hidden_biases.iter().flat_map(|v| v.to_le_bytes()));
+        out.extend(self.hidden_weights.iter().flat_map(|v| v.to_le_bytes()));
+        out.extend(self.output_bias.to_le_bytes());
+        out.extend(self.output_weights.iter().flat_map(|v| v.to_le_bytes()));
+        out
+    }
+}
+
+/// Reads little endian values off the front of a byte slice
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        ensure!(self.0.len() >= n, "unexpected end of file");
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn fill<T, const N: usize>(&mut self, out: &mut [T], f: fn([u8; N]) -> T) -> Result<()> {
+        let bytes = self.take(out.len() * N)?;
+        for (value, chunk) in out.iter_mut().zip(bytes.chunks_exact(N)) {
+            *value = f(chunk.try_into()?);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+//! The hot loops of inference, with AVX2 versions where the CPU has it
+
+/// Dot product of activations and weights, `a` and `b` must be the same length
+///
+/// Activations have to be at most 127 so pairs of products can't overflow an `i16`
+pub fn dot(a: &[u8], b: &[i8]) -> i32 {
+    debug_assert_eq!(a.len(), b.len());
+
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("avx2") {
+        // SAFETY: we just checked that the CPU supports AVX2
+        return unsafe { avx2::dot(a, b) };
+    }
+
+    dot_scalar(a, b)
+}
+
+/// Dot product without any SIMD, see [dot]
+pub fn dot_scalar(a: &[u8], b: &[i8]) -> i32 {
+    a.iter().zip(b).map(|(&a, &b)| a as i32 * b as i32).sum()
+}
+
+/// Adds `weights` to the accumulator
+///
+/// Plain loops over `i16`s, the compiler turns these into SIMD on its own
+#[inline(always)]
+pub fn add(values: &mut [i16], weights: &[i16]) {
+    for (v, &w) in values.iter_mut().zip(weights) {
+        *v = v.wrapping_add(w);
+    }
+}
+
+/// Subtracts `weights` from the accumulator
+#[inline(always)]
+pub fn sub(values: &mut [i16], weights: &[i16]) {
+    for (v, &w) in values.iter_mut().zip(weights) {
+        *v = v.wrapping_sub(w);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn dot(a: &[u8], b: &[i8]) -> i32 {
+        let chunks = a.len() / 32;
+        let ones = _mm256_set1_epi16(1);
+        let mut sum = _mm256_setzero_si256();
+
+        for i in 0..chunks {
+            let x = _mm256_loadu_si256(a.as_ptr().add(i * 32) as *const __m256i);
+            let y = _mm256_loadu_si256(b.as_ptr().add(i * 32) as *const __m256i);
+            // u8 * i8 summed in pairs to i16, then in pairs again to i32
+            let products = _mm256_maddubs_epi16(x, y);
+            sum = _mm256_add_epi32(sum, _mm256_madd_epi16(products, ones));
+        }
+
+        let mut lanes = [0i32; 8];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum);
+        let rest = chunks * 32;
+        lanes.iter().sum::<i32>() + super::dot_scalar(&a[rest..], &b[rest..])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dot() {
+        // long enough to use the SIMD path, with some left over for the scalar one
+        let a = (0..100).map(|i| (i * 37 % 128) as u8).collect::<Vec<_>>();
+        let b = (0..100)
+            .map(|i| (i * 91 % 256) as u8 as i8)
+            .collect::<Vec<_>>();
+        assert_eq!(dot(&a, &b), dot_scalar(&a, &b));
+    }
+}
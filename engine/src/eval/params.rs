@@ -0,0 +1,160 @@
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use strum::IntoEnumIterator;
+
+use crate::rules::piece::PieceKind;
+
+use super::{score::S, Params, Score};
+
+impl Params {
+    /// Every group of weights along with its name, in the order they are written out in
+    fn groups_mut(&mut self) -> Vec<(String, &mut [S])> {
+        let mut groups = vec![("material".to_string(), &mut self.material[..])];
+        for (kind, table) in PieceKind::iter().zip(&mut self.psqt) {
+            groups.push((format!("psqt.{kind:?}").to_lowercase(), &mut table[..]));
+        }
+        groups.extend([
+            (
+                "doubled".to_string(),
+                std::slice::from_mut(&mut self.doubled),
+            ),
+            (
+                "isolated".to_string(),
+                std::slice::from_mut(&mut self.isolated),
+            ),
+            (
+                "backward".to_string(),
+                std::slice::from_mut(&mut self.backward),
+            ),
+            ("connected".to_string(), &mut self.connected[..]),
+            ("passed".to_string(), &mut self.passed[..]),
+            ("mobility".to_string(), &mut self.mobility[..]),
+            (
+                "king_attack".to_string(),
+                std::slice::from_mut(&mut self.king_attack),
+            ),
+            ("shield".to_string(), &mut self.shield[..]),
+            ("storm".to_string(), &mut self.storm[..]),
+        ]);
+        groups
+    }
+
+    /// Every weight as a flat list, middlegame then endgame for each one
+    pub fn to_vec(&self) -> Vec<Score> {
+        let mut params = self.clone();
+        params
+            .groups_mut()
+            .into_iter()
+            .flat_map(|(_, group)| {
+                group
+                    .iter()
+                    .flat_map(|s| [s.mg(), s.eg()])
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Sets every weight from a flat list, the opposite of [Params::to_vec]
+    pub fn set_from_slice(&mut self, weights: &[Score]) {
+        let mut weights = weights.iter().array_chunks::<2>();
+        for (_, group) in self.groups_mut() {
+            for (s, [&mg, &eg]) in group.iter_mut().zip(&mut weights) {
+                *s = S(mg, eg);
+            }
+        }
+    }
+
+    /// Loads weights written by [Params::save], anything missing keeps its default
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        text.parse()
+            .with_context(|| format!("could not parse {}", path.display()))
+    }
+
+    /// Writes the weights out to a file that [Params::load] can read back
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_string())
+            .with_context(|| format!("could not write {}", path.display()))
+    }
+}
+
+/// One group of weights per line, the name followed by middlegame and endgame pairs
+impl Display for Params {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut params = self.clone();
+        for (name, group) in params.groups_mut() {
+            write!(f, "{name}")?;
+            for s in group.iter() {
+                write!(f, " {} {}", s.mg(), s.eg())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Params {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut params = Params::default();
+        let mut groups = params.groups_mut();
+
+        for (i, line) in s.lines().enumerate() {
+            // everything after a '#' is a comment
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(name) = words.next() else { continue };
+
+            let Some((_, group)) = groups.iter_mut().find(|(n, _)| n == name) else {
+                bail!("line {}: unknown weights \"{name}\"", i + 1);
+            };
+            let weights = words
+                .map(|w| w.parse::<Score>())
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("line {}: invalid weight", i + 1))?;
+            if weights.len() != group.len() * 2 {
+                bail!(
+                    "line {}: expected {} weights for \"{name}\", found {}",
+                    i + 1,
+                    group.len() * 2,
+                    weights.len()
+                );
+            }
+            for (s, pair) in group.iter_mut().zip(weights.chunks(2)) {
+                *s = S(pair[0], pair[1]);
+            }
+        }
+
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut params = Params {
+            isolated: S(-7, -3),
+            ..Default::default()
+        };
+        params.psqt[PieceKind::Knight as usize][10] = S(42, -42);
+
+        let text = format!("# tuned\n{params}");
+        assert_eq!(text.parse::<Params>().unwrap(), params);
+
+        let weights = params.to_vec();
+        let mut copy = Params::default();
+        copy.set_from_slice(&weights);
+        assert_eq!(copy, params);
+
+        assert!("passed 1 2 3".parse::<Params>().is_err());
+        assert!("nonsense 1 2".parse::<Params>().is_err());
+    }
+}
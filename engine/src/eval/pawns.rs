@@ -0,0 +1,205 @@
+use crate::{
+    chess::{square::Square, Team},
+    rules::piece::PieceKind,
+    state::{board_state::BoardState, State},
+};
+
+use super::{score::S, Params};
+
+/// What we know about the pawn structure of a position
+///
+/// Only depends on where the pawns are, so it can be cached by the pawn hash
+#[derive(Default, Clone, Copy, Debug)]
+pub struct PawnEntry {
+    /// The pawn hash this entry was computed for
+    pub key: u64,
+    /// Doubled, isolated, backward and connected pawn scores for each team
+    pub structure: [S; 2],
+    /// Squares with passed pawns on them for each team, one bit per square
+    pub passed: [u64; 2],
+    /// Squares attacked by the pawns of each team, one bit per square
+    pub attacks: [u64; 2],
+}
+
+/// Where the pawns of each team are
+struct Pawns([[bool; 64]; 2]);
+
+impl Pawns {
+    fn new(board: &BoardState) -> Self {
+        let mut pawns = [[false; 64]; 2];
+        for (i, &idx) in board.board().iter().enumerate() {
+            let piece = *idx.get(board.pieces());
+            if piece.kind() == Some(PieceKind::Pawn) {
+                let team = piece.team().expect("pawns have a team");
+                pawns[team as usize][i] = true;
+            }
+        }
+        Self(pawns)
+    }
+
+    /// Is there a pawn of `team` here
+    fn at(&self, team: Team, x: i8, y: i8) -> bool {
+        match Square::from_xy(x, y) {
+            Some(square) => self.0[team as usize][*square as usize],
+            None => false,
+        }
+    }
+
+    /// Is there a pawn of `team` on this file, on any rank `y` satisfies
+    fn on_file(&self, team: Team, x: i8, y: impl Fn(i8) -> bool) -> bool {
+        (0..8).filter(|&r| y(r)).any(|r| self.at(team, x, r))
+    }
+}
+
+/// Which way the pawns of a team move
+pub fn forward(team: Team) -> i8 {
+    match team {
+        Team::White => 1,
+        Team::Black => -1,
+    }
+}
+
+/// The rank of a square as seen by `team`, 0 being their back rank
+pub fn relative_rank(square: Square, team: Team) -> usize {
+    match team {
+        Team::White => square.y() as usize,
+        Team::Black => 7 - square.y() as usize,
+    }
+}
+
+impl PawnEntry {
+    /// Works out the pawn structure of a position from scratch
+    pub fn compute(params: &Params, board: &BoardState, key: u64) -> Self {
+        let pawns = Pawns::new(board);
+        let mut entry = Self {
+            key,
+            ..Default::default()
+        };
+
+        for team in [Team::Black, Team::White] {
+            let enemy = team.switch();
+            let f = forward(team);
+            let mut structure = S::default();
+
+            for i in (0..64).filter(|&i| pawns.0[team as usize][i]) {
+                let square = Square(i as u8);
+                let (x, y) = (square.x() as i8, square.y() as i8);
+                let ahead = |r: i8| (r - y) * f > 0;
+                let level_or_behind = |r: i8| (r - y) * f <= 0;
+
+                let doubled = pawns.on_file(team, x, ahead);
+                let isolated =
+                    !pawns.on_file(team, x - 1, |_| true) && !pawns.on_file(team, x + 1, |_| true);
+                let supported = pawns.at(team, x - 1, y - f) || pawns.at(team, x + 1, y - f);
+                let phalanx = pawns.at(team, x - 1, y) || pawns.at(team, x + 1, y);
+                let passed =
+                    !doubled && (x - 1..=x + 1).all(|file| !pawns.on_file(enemy, file, ahead));
+                // nothing can come up to defend it, and it can't safely step forward either
+                let backward = !isolated
+                    && !pawns.on_file(team, x - 1, level_or_behind)
+                    && !pawns.on_file(team, x + 1, level_or_behind)
+                    && (pawns.at(enemy, x - 1, y + 2 * f) || pawns.at(enemy, x + 1, y + 2 * f));
+
+                if doubled {
+                    structure += params.doubled;
+                }
+                if isolated {
+                    structure += params.isolated;
+                }
+                if backward {
+                    structure += params.backward;
+                }
+                if supported || phalanx {
+                    structure += params.connected[relative_rank(square, team)];
+                }
+                if passed {
+                    entry.passed[team as usize] |= 1 << i;
+                }
+                let attacks = [square.try_move(-1, f), square.try_move(1, f)];
+                for attack in attacks.into_iter().flatten() {
+                    entry.attacks[team as usize] |= 1 << *attack;
+                }
+            }
+            entry.structure[team as usize] = structure;
+        }
+
+        entry
+    }
+}
+
+/// Scores the passed pawns of each team
+///
+/// Pushing a passed pawn is worth less when the square in front of it is attacked,
+/// which depends on more than just the pawns so it can't be cached
+pub fn passed(state: &State, entry: &PawnEntry) -> [S; 2] {
+    let mut out = [S::default(); 2];
+    for team in [Team::Black, Team::White] {
+        let mut passed = entry.passed[team as usize];
+        while passed != 0 {
+            let square = Square(passed.trailing_zeros() as u8);
+            passed &= passed - 1;
+
+            let mut bonus = state.params.passed[relative_rank(square, team)];
+            let stop = square.try_move(0, forward(team));
+            if let Some(stop) = stop && state.moves.is_attacked(stop, team.switch()) {
+                bonus = S(bonus.mg() / 2, bonus.eg() / 2);
+            }
+            out[team as usize] += bonus;
+        }
+    }
+    out
+}
+
+/// A hash table of pawn structures, indexed by the pawn hash
+pub struct PawnTable {
+    entries: Vec<PawnEntry>,
+}
+
+impl PawnTable {
+    /// Creates a table with `2^bits` entries
+    pub fn new(bits: u32) -> Self {
+        Self {
+            entries: vec![PawnEntry::default(); 1 << bits],
+        }
+    }
+
+    /// Gets the pawn structure for a position, computing it if it isn't in the table
+    pub fn probe(&mut self, state: &State) -> PawnEntry {
+        let key = state.pawn_hash;
+        let slot = key as usize & (self.entries.len() - 1);
+        let entry = &mut self.entries[slot];
+        if entry.key != key {
+            *entry = PawnEntry::compute(&state.params, &state.board_state, key);
+        }
+        *entry
+    }
+
+    /// Forgets everything in the table
+    pub fn clear(&mut self) {
+        self.entries.fill(PawnEntry::default());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rules;
+
+    #[test]
+    fn test_pawn_structure() {
+        // white: backward d pawn, passed e pawn, doubled & isolated h pawns
+        // black: isolated c pawn
+        let state =
+            State::from_FEN("4k3/8/8/2p5/4P3/3P3P/7P/4K3 w - - 0 1", Rules::standard()).unwrap();
+        let params = &state.params;
+        let entry = PawnEntry::compute(params, &state.board_state, state.pawn_hash);
+
+        assert_eq!(entry.passed[Team::White as usize], 1 << 28 | 1 << 23);
+        assert_eq!(entry.passed[Team::Black as usize], 0);
+        assert_eq!(
+            entry.structure[Team::White as usize],
+            params.backward + params.connected[3] + params.isolated * 2 + params.doubled
+        );
+        assert_eq!(entry.structure[Team::Black as usize], params.isolated);
+    }
+}
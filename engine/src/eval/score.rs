@@ -0,0 +1,38 @@
+use derive_more::{Add, AddAssign, Neg, Sub, SubAssign, Sum};
+
+use super::Score;
+
+/// The phase of a game with all the non-pawn material still on the board
+pub const MAX_PHASE: i32 = 24;
+
+/// A pair of scores, one for the middlegame and one for the endgame
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Add, AddAssign, Sub, SubAssign, Neg, Sum)]
+pub struct S(pub Score, pub Score);
+
+impl S {
+    /// The middlegame score
+    pub fn mg(self) -> Score {
+        self.0
+    }
+
+    /// The endgame score
+    pub fn eg(self) -> Score {
+        self.1
+    }
+
+    /// Blends the middlegame and endgame scores according to the phase of the game
+    ///
+    /// A phase of [MAX_PHASE] is the opening, 0 is a bare endgame
+    pub fn taper(self, phase: i32) -> Score {
+        let phase = phase.clamp(0, MAX_PHASE);
+        (self.0 * phase + self.1 * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl std::ops::Mul<Score> for S {
+    type Output = S;
+
+    fn mul(self, rhs: Score) -> Self::Output {
+        S(self.0 * rhs, self.1 * rhs)
+    }
+}
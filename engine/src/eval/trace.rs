@@ -0,0 +1,187 @@
+use crossterm::style::Stylize;
+use std::fmt::{Display, Formatter};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::{
+    chess::{square::Square, Team},
+    state::State,
+};
+
+use super::{pawns::PawnEntry, psqt, score::S, Score};
+
+/// The terms that make up an evaluation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum Term {
+    Material,
+    Psqt,
+    Pawns,
+    PassedPawns,
+    Mobility,
+    KingSafety,
+}
+
+impl Term {
+    pub fn name(self) -> &'static str {
+        match self {
+            Term::Material => "Material",
+            Term::Psqt => "Piece-square",
+            Term::Pawns => "Pawn structure",
+            Term::PassedPawns => "Passed pawns",
+            Term::Mobility => "Mobility",
+            Term::KingSafety => "King safety",
+        }
+    }
+}
+
+/// A breakdown of how a position was evaluated
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trace {
+    /// The score of every term for each team, from that team's perspective
+    pub terms: [[S; 2]; 6],
+    /// The phase of the game, see [super::phase]
+    pub phase: i32,
+    /// The team whose turn it is
+    pub turn: Team,
+    /// The network's score when the state has one, which is used instead of the terms
+    pub network: Option<Score>,
+}
+
+impl std::ops::Index<Term> for Trace {
+    type Output = [S; 2];
+
+    fn index(&self, term: Term) -> &Self::Output {
+        &self.terms[term as usize]
+    }
+}
+
+impl Trace {
+    /// The score of a term from White's perspective
+    pub fn net(&self, term: Term) -> S {
+        let [black, white] = self[term];
+        white - black
+    }
+
+    /// The sum of every term from White's perspective
+    pub fn total(&self) -> S {
+        Term::iter().map(|term| self.net(term)).sum()
+    }
+
+    /// The final score from the perspective of the side to move, same as [super::evaluate]
+    pub fn score(&self) -> Score {
+        self.network.unwrap_or_else(|| self.handcrafted())
+    }
+
+    /// The score of the terms from the perspective of the side to move
+    pub fn handcrafted(&self) -> Score {
+        let score = self.total().taper(self.phase);
+        match self.turn {
+            Team::White => score,
+            Team::Black => -score,
+        }
+    }
+}
+
+/// Evaluates the position, keeping track of what every term contributed
+///
+/// The terms are always the handcrafted evaluation, a network's score is reported next to them
+pub fn trace(state: &State) -> Trace {
+    let params = &state.params;
+    let board = &state.board_state;
+
+    // material and piece-square scores are usually kept together, split them up again
+    let mut material = [S::default(); 2];
+    let mut psqt = [S::default(); 2];
+    for (i, &idx) in board.board().iter().enumerate() {
+        let piece = *idx.get(board.pieces());
+        let (Some(kind), Some(team)) = (piece.kind(), piece.team()) else { continue };
+        material[team as usize] += params.material[kind as usize];
+        psqt[team as usize] += params.psqt[kind as usize][psqt::index(Square(i as u8), team)];
+    }
+
+    let pawns = PawnEntry::compute(params, board, state.pawn_hash);
+    let [structure, passed, mobility, king_safety] = super::terms(state, &pawns);
+
+    Trace {
+        terms: [material, psqt, structure, passed, mobility, king_safety],
+        phase: state.phase,
+        turn: state.turn,
+        network: state.nnue.as_ref().map(|nnue| nnue.evaluate(state.turn)),
+    }
+}
+
+fn fmt_s(s: S) -> String {
+    format!("{:>6}{:>6}", s.mg(), s.eg())
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", "Evaluation".red())?;
+        writeln!(
+            f,
+            "{:16}{}{}{:>12}",
+            "",
+            format!("{:>12}", "White").blue(),
+            format!("{:>12}", "Black").green(),
+            "Total"
+        )?;
+        writeln!(
+            f,
+            "{:16}{}",
+            "",
+            format!("{:>6}{:>6}", "mg", "eg").repeat(3)
+        )?;
+
+        for term in Term::iter() {
+            let [black, white] = self[term];
+            writeln!(
+                f,
+                "{:16}{}{}{}",
+                term.name(),
+                fmt_s(white).blue(),
+                fmt_s(black).green(),
+                fmt_s(self.net(term))
+            )?;
+        }
+
+        let total = self.total();
+        let title = format!("{:16}", "Total").red();
+        writeln!(f, "{title}{:24}{}", "", fmt_s(total))?;
+        writeln!(
+            f,
+            "Phase {}/{}, blended {:+} for White, {:+} for the side to move",
+            self.phase,
+            super::score::MAX_PHASE,
+            total.taper(self.phase),
+            self.handcrafted()
+        )?;
+        if let Some(score) = self.network {
+            let title = format!("{:16}", "Network").red();
+            writeln!(f, "{title}{score:+} for the side to move, used instead")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{eval::evaluate, rules::Rules};
+
+    #[test]
+    fn test_trace() {
+        let state = State::from_FEN(
+            "r1bqk2r/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP3PPP/R1BQKB1R b KQkq - 0 7",
+            Rules::standard(),
+        )
+        .unwrap();
+        let trace = trace(&state);
+
+        assert_eq!(trace.score(), evaluate(&state));
+        assert_eq!(
+            trace.net(Term::Material) + trace.net(Term::Psqt),
+            state.score
+        );
+        assert_eq!(trace[Term::Material][0], trace[Term::Material][1]);
+    }
+}
@@ -0,0 +1,434 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    chess::Team,
+    move_gen::{moves::Move, notation::UciMove},
+    rules::{fen::START_FEN, Rules},
+    search::alphabeta::SquareMove,
+    state::State,
+};
+
+/// How a game ended, as written at the end of its movetext
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// Still going, or the result isn't known
+    #[default]
+    Unknown,
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        };
+        write!(f, "{text}")
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "1-0" => GameResult::WhiteWins,
+            "0-1" => GameResult::BlackWins,
+            "1/2-1/2" => GameResult::Draw,
+            "*" => GameResult::Unknown,
+            _ => bail!("invalid result {s}"),
+        })
+    }
+}
+
+/// Where a node is in the tree of a [Game]
+pub type NodeId = usize;
+
+/// A position in a game, and the move that was played to get there
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    /// `None` at the start of the game
+    pub m: Option<SquareMove>,
+    pub parent: Option<NodeId>,
+    /// The moves played from here, the first one is the main line and the rest are variations
+    pub children: Vec<NodeId>,
+    /// How many moves have been played since the start of the game
+    pub ply: usize,
+    /// Numeric annotation glyphs, `$1` or `!` is a good move, `$2` or `?` a mistake and so on
+    pub nags: Vec<u8>,
+    /// The comment before the move, only written at the start of a variation
+    pub comment_before: Option<String>,
+    /// The comment after the move, at the start of the game it's about the whole game
+    pub comment: Option<String>,
+}
+
+impl Node {
+    fn new(m: Option<SquareMove>, parent: Option<NodeId>, ply: usize) -> Self {
+        Self {
+            m,
+            parent,
+            children: Vec::new(),
+            ply,
+            nags: Vec::new(),
+            comment_before: None,
+            comment: None,
+        }
+    }
+}
+
+/// The record of a game: where it started, the moves played with any variations,
+/// and the tags that say who played it, when and how it ended
+///
+/// A cursor points at one position in the game, it can be moved through the moves and
+/// playing a move from it adds the move if it's new, starting a variation if there's
+/// already a move there
+#[derive(Clone, Debug)]
+pub struct Game {
+    /// Tag pairs like `White`, `Date` or `TimeControl`, in the order they're written
+    pub tags: Vec<(String, String)>,
+    pub result: GameResult,
+    /// The number of the first move, usually 1
+    pub first_move: u32,
+    /// The position the game starts from
+    start: State,
+    /// Every position of the game, the start is the first one
+    nodes: Vec<Node>,
+    cursor: NodeId,
+    /// The position at the cursor
+    state: State,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(
+            State::from_FEN(START_FEN, Rules::standard()).expect("the start position is valid"),
+        )
+    }
+}
+
+impl Game {
+    /// The root of the tree, before any move has been played
+    pub const ROOT: NodeId = 0;
+
+    pub fn new(start: State) -> Self {
+        Self {
+            tags: Vec::new(),
+            result: GameResult::Unknown,
+            first_move: 1,
+            state: start.clone(),
+            start,
+            nodes: vec![Node::new(None, None, 0)],
+            cursor: Self::ROOT,
+        }
+    }
+
+    /// A game starting from a FEN, which is kept in the tags for writing it out
+    pub fn from_fen(fen: &str) -> Result<Self> {
+        let state = State::from_FEN(fen, Rules::standard())?;
+        let mut game = Self::new(state);
+        game.first_move = match fen.split(' ').nth(5) {
+            Some(n) => n
+                .parse()
+                .with_context(|| format!("invalid move number {n}"))?,
+            None => 1,
+        };
+        if fen != START_FEN {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", fen);
+        }
+        Ok(game)
+    }
+
+    /// The value of a tag, if the game has it
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets a tag, keeping its place if it's already there
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn white(&self) -> Option<&str> {
+        self.tag("White")
+    }
+
+    pub fn black(&self) -> Option<&str> {
+        self.tag("Black")
+    }
+
+    /// When the game was played, as `YYYY.MM.DD` with `??` for anything unknown
+    pub fn date(&self) -> Option<&str> {
+        self.tag("Date")
+    }
+
+    /// The time control, like `40/7200:3600` or `300+2`
+    pub fn time_control(&self) -> Option<&str> {
+        self.tag("TimeControl")
+    }
+
+    /// The position the game starts from
+    pub fn start(&self) -> &State {
+        &self.start
+    }
+
+    pub fn node(&self, node: NodeId) -> &Node {
+        &self.nodes[node]
+    }
+
+    pub fn node_mut(&mut self, node: NodeId) -> &mut Node {
+        &mut self.nodes[node]
+    }
+
+    /// The node the cursor is on
+    pub fn cursor(&self) -> NodeId {
+        self.cursor
+    }
+
+    /// The position at the cursor
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// How many moves have been played to get to the cursor
+    pub fn ply(&self) -> usize {
+        self.nodes[self.cursor].ply
+    }
+
+    /// The number of the move played after `ply` moves, like the 12 of `12. Nf3` or `12... Nf6`
+    pub fn move_number(&self, ply: usize) -> u32 {
+        let ply = ply as u32 + (self.start.turn == Team::Black) as u32;
+        self.first_move + ply / 2
+    }
+
+    /// The moves from the start of the game to `node`
+    pub fn line_to(&self, mut node: NodeId) -> Vec<SquareMove> {
+        let mut line = Vec::new();
+        while let Node {
+            m: Some(m),
+            parent: Some(parent),
+            ..
+        } = self.nodes[node]
+        {
+            line.push(m);
+            node = parent;
+        }
+        line.reverse();
+        line
+    }
+
+    /// The moves of the main line, from the start to the end of the game
+    pub fn main_line(&self) -> Vec<SquareMove> {
+        let mut line = Vec::new();
+        let mut node = Self::ROOT;
+        while let Some(&child) = self.nodes[node].children.first() {
+            line.extend(self.nodes[child].m);
+            node = child;
+        }
+        line
+    }
+
+    /// Adds a move played from `parent`, returning its node
+    ///
+    /// A move that's already there is just followed, a new one is added after the ones
+    /// that are, so it continues the main line if there aren't any and is a variation if
+    /// there are. The move isn't checked, that's up to whoever played it
+    pub fn add_move(&mut self, parent: NodeId, m: SquareMove) -> NodeId {
+        let existing = self.nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].m == Some(m));
+        if let Some(node) = existing {
+            return node;
+        }
+        let node = self.nodes.len();
+        let ply = self.nodes[parent].ply + 1;
+        self.nodes.push(Node::new(Some(m), Some(parent), ply));
+        self.nodes[parent].children.push(node);
+        node
+    }
+
+    /// Plays a move from the cursor and moves the cursor to it, see [Game::add_move]
+    ///
+    /// Only a move that isn't in the game yet has to be checked
+    pub fn play(&mut self, m: SquareMove) -> Result<NodeId> {
+        let known = self.nodes[self.cursor]
+            .children
+            .iter()
+            .any(|&child| self.nodes[child].m == Some(m));
+        if !known {
+            UciMove::from(m).resolve(&mut self.state)?;
+        }
+        let node = self.add_move(self.cursor, m);
+        self.state.make_move(m.0, m.1);
+        self.cursor = node;
+        Ok(node)
+    }
+
+    /// Plays a move written in SAN from the cursor, see [Game::play]
+    pub fn play_san(&mut self, san: &str) -> Result<NodeId> {
+        let m = Move::from_san(san, &mut self.state)?;
+        let from = self.state.board_state.square_of(m.piece);
+        self.play((from, m.to))
+    }
+
+    /// Takes the cursor back a move, returning false at the start of the game
+    pub fn back(&mut self) -> bool {
+        let Some(parent) = self.nodes[self.cursor].parent else { return false };
+        self.state.unmake_move();
+        self.cursor = parent;
+        true
+    }
+
+    /// Takes the cursor forward along the main line from it, returning false at the end
+    pub fn forward(&mut self) -> bool {
+        self.branch(0)
+    }
+
+    /// Takes the cursor forward into one of the moves from it, 0 is the main line and
+    /// the rest are the variations, returning false if there isn't one
+    pub fn branch(&mut self, variation: usize) -> bool {
+        let Some(&child) = self.nodes[self.cursor].children.get(variation) else { return false };
+        let (from, to) = self.nodes[child].m.expect("only the root has no move");
+        self.state.make_move(from, to);
+        self.cursor = child;
+        true
+    }
+
+    /// Moves the cursor to the start of the game
+    pub fn to_start(&mut self) {
+        while self.back() {}
+    }
+
+    /// Moves the cursor to the end of the line it's on, following the main line from it
+    pub fn to_end(&mut self) {
+        while self.forward() {}
+    }
+
+    /// Moves the cursor to a ply of the line it's on, going back or following the main
+    /// line from it, returning false if the line is too short
+    pub fn go_to_ply(&mut self, ply: usize) -> bool {
+        while self.ply() > ply {
+            self.back();
+        }
+        while self.ply() < ply {
+            if !self.forward() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Moves the cursor to any node in the game
+    pub fn go_to(&mut self, node: NodeId) {
+        let line = self.line_to(node);
+        // back to where the lines split, then along the new one
+        let shared = line
+            .iter()
+            .zip(self.line_to(self.cursor))
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        self.go_to_ply(shared);
+        for &m in &line[shared..] {
+            self.play(m).expect("moves in the game are legal");
+        }
+    }
+
+    /// Makes the variation the cursor is in the main line, where it branches off
+    pub fn promote_variation(&mut self) {
+        let mut node = self.cursor;
+        while let Some(parent) = self.nodes[node].parent {
+            let children = &mut self.nodes[parent].children;
+            if children[0] != node {
+                let i = children
+                    .iter()
+                    .position(|&child| child == node)
+                    .expect("is a child");
+                children[..=i].rotate_right(1);
+                return;
+            }
+            node = parent;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn m(uci: &str) -> SquareMove {
+        let m = uci.parse::<UciMove>().unwrap();
+        (m.from, m.to)
+    }
+
+    #[test]
+    fn test_game() {
+        let mut game = Game::default();
+        for uci in ["e2e3", "e7e6", "g1f3", "b8c6"] {
+            game.play(m(uci)).unwrap();
+        }
+        assert!(game.play(m("a1a3")).is_err());
+        assert_eq!(game.ply(), 4);
+        assert_eq!(game.move_number(game.ply()), 3);
+
+        // a different second move for black starts a variation
+        assert!(game.go_to_ply(3));
+        let variation = game.play(m("d7d6")).unwrap();
+        game.play(m("d2d3")).unwrap();
+        assert_eq!(
+            game.node(game.node(variation).parent.unwrap())
+                .children
+                .len(),
+            2
+        );
+        assert_eq!(game.main_line().len(), 4);
+        assert_eq!(game.line_to(game.cursor())[3], m("d7d6"));
+
+        // back to the start and along the main line
+        game.to_start();
+        assert_eq!(game.state().history.len(), 0);
+        game.to_end();
+        assert_eq!(game.ply(), 4);
+        assert!(!game.forward());
+
+        // jumping between lines, then making the variation the main line
+        game.go_to(variation);
+        assert_eq!(game.ply(), 4);
+        assert_eq!(
+            game.line_to(game.cursor()),
+            game.state()
+                .history
+                .iter()
+                .map(|undo| (undo.from, undo.to))
+                .collect::<Vec<_>>()
+        );
+        game.forward();
+        game.promote_variation();
+        assert_eq!(game.main_line()[3..], [m("d7d6"), m("d2d3")]);
+        game.go_to_ply(3);
+        assert!(game.branch(1));
+        assert_eq!(game.line_to(game.cursor())[3], m("b8c6"));
+        assert!(!game.branch(1));
+
+        let mut game = Game::from_fen("6k1/5ppp/8/8/8/8/8/3Q2K1 b - - 0 30").unwrap();
+        assert_eq!(game.tag("FEN"), Some("6k1/5ppp/8/8/8/8/8/3Q2K1 b - - 0 30"));
+        assert_eq!(game.move_number(0), 30);
+        assert_eq!(game.move_number(1), 31);
+        game.set_tag("White", "Nobody");
+        game.set_tag("White", "Somebody");
+        assert_eq!(game.white(), Some("Somebody"));
+    }
+}
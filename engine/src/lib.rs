@@ -11,13 +11,14 @@
 use derive_more::{Deref, DerefMut};
 
 pub mod chess;
+pub mod epd;
+pub mod eval;
+pub mod game;
 pub mod misc;
 pub mod move_gen;
+pub mod pgn;
+pub mod protocol;
 pub mod rules;
+pub mod search;
 pub mod state;
-
-/// Initialize some static muts (i know i know)
-pub fn init() {
-    state::board_state::init();
-    move_gen::normal::init();
-}
+pub mod tune;
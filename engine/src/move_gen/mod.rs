@@ -2,6 +2,7 @@ pub mod attack;
 pub mod generator;
 pub mod moves;
 pub mod normal;
+pub mod notation;
 
 use crate::{
     chess::{index::Index, square::Square, Team},
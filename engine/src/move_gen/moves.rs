@@ -17,7 +17,7 @@ use crate::state::board_state::BoardState;
 use super::attack::{Attacked, SlidingAttacks};
 
 /// A move from one square to another
-#[derive(Hash, PartialEq, Eq, Debug, Clone)]
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
 pub struct Move {
     pub piece: Index<Piece>,
     pub to: Square,
@@ -55,6 +55,11 @@ impl Moves {
         self.attacked[team].sliding[square]
     }
 
+    /// Returns true if `team` is attacking this square with any piece
+    pub fn is_attacked(&self, square: Square, team: Team) -> bool {
+        self.attacked[team].is_attacked(square)
+    }
+
     /// Adds a piece's moves to itself
     pub fn add_piece(
         &mut self,
@@ -84,6 +89,11 @@ impl Moves {
         moves
     }
 
+    /// Gets all the moves for every piece
+    pub fn iter(&self) -> impl Iterator<Item = &Move> {
+        self.moves.iter()
+    }
+
     /// Gets all the moves for a particular piece
     pub fn filter(&self, piece: Index<Piece>) -> impl Iterator<Item = &Move> {
         self.moves.iter().filter(move |m| m.piece == piece)
@@ -186,6 +196,17 @@ impl Moves {
         self.moves.remove(&Move { piece: idx, to: square })
     }
 
+    /// Inserts a move that doesn't attack the square it goes to, like a pawn push
+    pub fn insert_quiet(&mut self, idx: Index<Piece>, square: Square) {
+        let check = self.moves.insert(Move { piece: idx, to: square });
+        debug_assert!(check, "Move already exists: {:?} -> {:?}\n", idx, square);
+    }
+
+    /// Removes a move that doesn't attack the square it goes to, returns whether the move was there to begin with
+    pub fn remove_quiet(&mut self, idx: Index<Piece>, square: Square) -> bool {
+        self.moves.remove(&Move { piece: idx, to: square })
+    }
+
     /// Inserts a *good* move into the list of moves
     pub fn insert_good(&mut self, idx: Index<Piece>, square: Square, team: Team) {
         self.moves.insert(Move {
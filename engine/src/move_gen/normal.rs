@@ -1,11 +1,12 @@
 use derive_more::{Deref, DerefMut};
+use strum::IntoEnumIterator;
 use strum_macros::Display;
 
 use crate::{
     chess::{index::Index, square::Square, Team},
     rules::piece::Piece,
     state::{
-        board_state::{BoardState, PIECES},
+        board_state::BoardState,
         State,
     },
 };
@@ -87,15 +88,11 @@ impl NormalMoves {
     }
 }
 
-static mut MOVES: Vec<NormalMoves> = vec![];
-
-pub fn init() {
-    unsafe {
-        PIECES.iter().for_each(|i| {
-            MOVES.push(i.moves());
-        });
-    }
-}
+/// The normal moves of every piece, indexed by [Piece]
+#[ctor::ctor]
+static MOVES: Vec<NormalMoves> = Piece::iter()
+    .map(|p| p.piece().map(|p| p.moves()).unwrap_or_default())
+    .collect();
 
 impl Moves {
     /// Add the normal moves for a piece to a list of moves
@@ -106,10 +103,10 @@ impl Moves {
         square: Square,
         team: Team,
     ) {
-        let moves = unsafe { &MOVES[*idx.get(board.pieces()) as usize] };
+        let moves = &MOVES[*idx.get(board.pieces()) as usize];
         for relative in moves.iter() {
             // try and get the square
-            let Some(square) = relative.pos.try_add(square) else { return };
+            let Some(square) = relative.pos.try_add(square) else { continue };
 
             use NormalMoveType::*;
             let capture = matches!(relative.move_type, Attack | Normal);
@@ -134,7 +131,11 @@ impl Moves {
             }
             if _move {
                 if info.is_none() {
-                    self.insert(idx, square, team);
+                    // moves that can't capture don't attack the square
+                    match relative.move_type {
+                        Move => self.insert_quiet(idx, square),
+                        _ => self.insert(idx, square, team),
+                    }
                     self.insert_callback(square, idx);
                 }
             }
@@ -149,10 +150,10 @@ impl Moves {
         square: Square,
         team: Team,
     ) {
-        let moves = unsafe { &MOVES[*idx.get(board.pieces()) as usize] };
+        let moves = &MOVES[*idx.get(board.pieces()) as usize];
         for relative in moves.iter() {
             // try and get the square
-            let Some(square) = relative.pos.try_add(square) else { return };
+            let Some(square) = relative.pos.try_add(square) else { continue };
 
             use NormalMoveType::*;
             let capture = matches!(relative.move_type, Attack | Normal);
@@ -177,10 +178,88 @@ impl Moves {
             }
             if _move {
                 if info.is_none() {
-                    debug_assert!(self.remove(idx, square, team));
+                    match relative.move_type {
+                        Move => debug_assert!(self.remove_quiet(idx, square)),
+                        _ => debug_assert!(self.remove(idx, square, team)),
+                    }
                     self.remove_callback(square, idx);
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rules;
+
+    /// The squares the piece on `square` can move to
+    fn targets(state: &State, square: Square) -> Vec<Square> {
+        let idx = state.board_state.board()[square];
+        let mut targets = state.moves.filter(idx).map(|m| m.to).collect::<Vec<_>>();
+        targets.sort();
+        targets
+    }
+
+    /// Counts the positions `depth` moves ahead, kings can be captured like anything else
+    fn perft(state: &mut State, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let board = &state.board_state;
+        let moves = board
+            .board()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &idx)| board.get_info(idx).map(|info| info.team) == Some(state.turn))
+            .flat_map(|(i, &idx)| state.moves.filter(idx).map(move |m| (Square(i as u8), m.to)))
+            .collect::<Vec<_>>();
+        moves
+            .into_iter()
+            .map(|(from, to)| {
+                state.make_move(from, to);
+                let count = perft(state, depth - 1);
+                state.unmake_move();
+                count
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_pawn_moves() {
+        let sq = |x: u8, y: u8| Square::from_xy(x, y).unwrap();
+        let state =
+            State::from_FEN("4k3/8/8/8/8/n1n3n1/1P5P/4K3 w - - 0 1", Rules::standard()).unwrap();
+
+        // pawns capture on both sides
+        assert_eq!(targets(&state, sq(1, 1)), [sq(0, 2), sq(1, 2), sq(2, 2)]);
+        // a capture off the edge of the board doesn't stop the one on the other side
+        assert_eq!(targets(&state, sq(7, 1)), [sq(6, 2), sq(7, 2)]);
+        assert!(state.moves.threat_at(sq(6, 2), Team::White) > 0);
+    }
+
+    #[test]
+    fn test_knight_and_king_moves() {
+        let sq = |x: u8, y: u8| Square::from_xy(x, y).unwrap();
+        let state =
+            State::from_FEN("k7/8/8/3N4/8/2p5/8/N6K w - - 0 1", Rules::standard()).unwrap();
+
+        // one of them takes the pawn
+        assert_eq!(targets(&state, sq(3, 4)).len(), 8);
+        assert!(targets(&state, sq(3, 4)).contains(&sq(2, 2)));
+        // in the corners
+        assert_eq!(targets(&state, sq(0, 0)), [sq(2, 1), sq(1, 2)]);
+        assert_eq!(targets(&state, sq(7, 0)), [sq(6, 0), sq(6, 1), sq(7, 1)]);
+        assert_eq!(targets(&state, sq(0, 7)).len(), 3);
+    }
+
+    #[test]
+    fn test_perft() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+        // a pawn push or a knight move each, pawns only move one square
+        assert_eq!(perft(&mut state, 1), 12);
+        assert_eq!(perft(&mut state, 2), 144);
+    }
+}
@@ -0,0 +1,347 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    chess::{index::Index, square::Square},
+    rules::piece::Piece,
+    state::State,
+};
+
+use super::moves::Move;
+
+/// A move in the coordinate notation UCI uses, like `e2e4`, or `e7e8q` for a promotion
+///
+/// This is just the text, [UciMove::resolve] finds the move it means in a position
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UciMove {
+    pub from: Square,
+    pub to: Square,
+    /// The lowercase FEN letter of the piece a pawn promotes to
+    pub promotion: Option<char>,
+}
+
+impl Display for UciMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        match self.promotion {
+            Some(ch) => write!(f, "{ch}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for UciMove {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let context = || format!("invalid move {s}");
+        if !s.is_ascii() || !(4..=5).contains(&s.len()) {
+            bail!(context());
+        }
+        let promotion = match s[4..].chars().next() {
+            Some(ch) if ch.is_ascii_alphabetic() => Some(ch.to_ascii_lowercase()),
+            Some(_) => bail!(context()),
+            None => None,
+        };
+        Ok(Self {
+            from: s[..2].parse().with_context(context)?,
+            to: s[2..4].parse().with_context(context)?,
+            promotion,
+        })
+    }
+}
+
+impl From<(Square, Square)> for UciMove {
+    fn from((from, to): (Square, Square)) -> Self {
+        Self {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+}
+
+impl UciMove {
+    /// Finds the legal move this is in the position
+    ///
+    /// The rules don't have promotions yet, so a move with a promotion is never legal
+    pub fn resolve(&self, state: &mut State) -> Result<Move> {
+        let piece = state.board_state.board()[self.from];
+        let m = state
+            .moves
+            .iter()
+            .find(|m| m.piece == piece && m.to == self.to)
+            .copied();
+        match m {
+            Some(m) if self.promotion.is_none() && state.is_legal(self.from, self.to) => Ok(m),
+            _ => bail!("illegal move {self}"),
+        }
+    }
+}
+
+impl Move {
+    /// The move in coordinate notation, in the position it's a move in
+    pub fn to_uci(&self, state: &State) -> UciMove {
+        UciMove::from((state.board_state.square_of(self.piece), self.to))
+    }
+}
+
+/// The letter a piece is written with in SAN, its uppercase FEN letter, or `None` for pawns
+fn san_letter(state: &State, piece: Index<Piece>) -> Option<char> {
+    let ch = state
+        .board_state
+        .get_info(piece)?
+        .fen_ch?
+        .to_ascii_uppercase();
+    (ch != 'P').then_some(ch)
+}
+
+impl Move {
+    /// The move in standard algebraic notation (SAN), like `Nbd2`, `exd5` or `Qh7#`
+    ///
+    /// Only as much of the starting square is given as it takes to tell the move apart
+    /// from the other legal ones
+    pub fn to_san(&self, state: &mut State) -> String {
+        let from = state.board_state.square_of(self.piece);
+        let letter = san_letter(state, self.piece);
+        let capture = state.board_state.get_info(self.to).is_some();
+        let square = from.to_string();
+        let (file, rank) = square.split_at(1);
+
+        let mut san = String::new();
+        match letter {
+            Some(letter) => {
+                san.push(letter);
+                // the other pieces of the same kind that could go there too
+                let others = state
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|m| m.to == self.to && m.piece != self.piece)
+                    .filter(|m| san_letter(state, m.piece) == Some(letter))
+                    .map(|m| state.board_state.square_of(m.piece))
+                    .collect::<Vec<_>>();
+                let same_file = others.iter().any(|other| other.x() == from.x());
+                let same_rank = others.iter().any(|other| other.y() == from.y());
+                match (others.is_empty(), same_file, same_rank) {
+                    (true, ..) => {}
+                    (false, false, _) => san.push_str(file),
+                    (false, true, false) => san.push_str(rank),
+                    (false, true, true) => {
+                        san.push_str(file);
+                        san.push_str(rank);
+                    }
+                }
+            }
+            None if capture => san.push_str(file),
+            None => {}
+        }
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&self.to.to_string());
+
+        state.make_move(from, self.to);
+        if state.in_check(state.turn) {
+            san.push(match state.legal_moves().is_empty() {
+                true => '#',
+                false => '+',
+            });
+        }
+        state.unmake_move();
+        san
+    }
+
+    /// Finds the legal move a SAN move means in the position
+    ///
+    /// Common variants are accepted too: a missing or extra `x`, the whole starting square,
+    /// `0-0` for castling and annotations like `+`, `#` or `!?` on the end. The rules don't
+    /// have castling or promotions yet, so those are never legal
+    pub fn from_san(san: &str, state: &mut State) -> Result<Self> {
+        let text = san
+            .trim()
+            .trim_end_matches("e.p.")
+            .trim_end()
+            .trim_end_matches(['+', '#', '!', '?']);
+        if !text.is_ascii() || text.len() < 2 {
+            bail!("invalid move {san}");
+        }
+
+        let castling = match text.replace('0', "O").as_str() {
+            "O-O" => Some(2),
+            "O-O-O" => Some(-2),
+            _ => None,
+        };
+        if let Some(offset) = castling {
+            let m = state.legal_moves().into_iter().find(|m| {
+                let from = state.board_state.square_of(m.piece);
+                san_letter(state, m.piece) == Some('K')
+                    && m.to.y() == from.y()
+                    && m.to.x() as i8 - from.x() as i8 == offset
+            });
+            return m.with_context(|| format!("illegal move {san}"));
+        }
+
+        // a promotion is only ever legal once the rules have them
+        let (text, promotion) = match text.split_once('=') {
+            Some((text, promotion)) => (text, !promotion.is_empty()),
+            None => match text.strip_suffix(|ch: char| ch.is_ascii_uppercase()) {
+                Some(text) => (text, true),
+                None => (text, false),
+            },
+        };
+        if text.len() < 2 {
+            bail!("invalid move {san}");
+        }
+
+        let (text, to) = text.split_at(text.len() - 2);
+        let to = to
+            .parse::<Square>()
+            .with_context(|| format!("invalid move {san}"))?;
+        let (letter, hints) = match text.chars().next() {
+            Some(ch) if ch.is_ascii_uppercase() => (Some(ch).filter(|&ch| ch != 'P'), &text[1..]),
+            _ => (None, text),
+        };
+        let mut file = None;
+        let mut rank = None;
+        for ch in hints.chars() {
+            match ch {
+                'a'..='h' => file = Some(ch as u8 - b'a'),
+                '1'..='8' => rank = Some(ch as u8 - b'1'),
+                'x' | ':' | '-' => {}
+                _ => bail!("invalid move {san}"),
+            }
+        }
+
+        let matches = state
+            .legal_moves()
+            .into_iter()
+            .filter(|m| {
+                let from = state.board_state.square_of(m.piece);
+                m.to == to
+                    && san_letter(state, m.piece) == letter
+                    && !matches!(file, Some(x) if x != from.x())
+                    && !matches!(rank, Some(y) if y != from.y())
+            })
+            .collect::<Vec<_>>();
+        match matches[..] {
+            [m] if !promotion => Ok(m),
+            [_, _, ..] => bail!("ambiguous move {san}"),
+            _ => bail!("illegal move {san}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rules;
+
+    #[test]
+    fn test_uci_move() {
+        assert_eq!("e4".parse::<Square>().unwrap(), Square(28));
+        assert_eq!("h8".parse::<Square>().unwrap().to_string(), "h8");
+        for invalid in ["i1", "e9", "e", "e44", ""] {
+            assert!(invalid.parse::<Square>().is_err(), "{invalid}");
+        }
+
+        let m = "e7e8Q".parse::<UciMove>().unwrap();
+        assert_eq!(m.promotion, Some('q'));
+        assert_eq!(m.to_string(), "e7e8q");
+        for invalid in ["e2", "e2e9", "e2e4+", "e2e41", "é2e4"] {
+            assert!(invalid.parse::<UciMove>().is_err(), "{invalid}");
+        }
+
+        let fen = "4k3/4P3/8/8/8/8/3P4/4K3 w - - 0 1";
+        let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+        let m = "d2d3".parse::<UciMove>().unwrap();
+        assert_eq!(m.resolve(&mut state).unwrap().to_uci(&state), m);
+        // the pawn on e7 is blocked, kings only go one square and it's not black's turn
+        for illegal in ["e7e8q", "e7e8", "e1e3", "e8d8", "a1a2"] {
+            let m = illegal.parse::<UciMove>().unwrap();
+            assert!(m.resolve(&mut state).is_err(), "{illegal}");
+        }
+        assert!(state.history.is_empty());
+    }
+
+    /// The SAN of a move given in coordinates
+    fn san(state: &mut State, uci: &str) -> String {
+        let m = uci.parse::<UciMove>().unwrap().resolve(state).unwrap();
+        m.to_san(state)
+    }
+
+    #[test]
+    fn test_to_san() {
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+        let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+        assert_eq!(san(&mut state, "g1f3"), "Nf3");
+        assert_eq!(san(&mut state, "e4d5"), "exd5");
+        assert_eq!(san(&mut state, "e4e5"), "e5");
+        assert_eq!(san(&mut state, "f1b5"), "Bb5+");
+
+        // the rooks share a rank, then a file
+        let fen = "6k1/8/8/R7/8/8/8/R4R1K w - - 0 1";
+        let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+        assert_eq!(san(&mut state, "a1b1"), "Rab1");
+        assert_eq!(san(&mut state, "f1b1"), "Rfb1");
+        assert_eq!(san(&mut state, "a1a2"), "R1a2");
+        assert_eq!(san(&mut state, "a5a4"), "R5a4");
+
+        // one queen shares a file and a rank with the others
+        let fen = "6k1/8/8/8/8/Q7/8/Q1Q4K w - - 0 1";
+        let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+        assert_eq!(san(&mut state, "a1b2"), "Qa1b2");
+        assert_eq!(san(&mut state, "a3b2"), "Q3b2");
+        assert_eq!(san(&mut state, "c1b2"), "Qcb2");
+        assert_eq!(san(&mut state, "c1c2"), "Qc2");
+
+        let fen = "6k1/5ppp/8/8/8/8/8/3Q2K1 w - - 0 1";
+        let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+        assert_eq!(san(&mut state, "d1d8"), "Qd8#");
+        assert!(state.history.is_empty());
+    }
+
+    #[test]
+    fn test_from_san() {
+        let uci = |state: &mut State, text: &str| {
+            Move::from_san(text, state).map(|m| m.to_uci(state).to_string())
+        };
+        let fen = "6k1/8/8/R7/8/Q7/8/Q1Q2R1K w - - 0 1";
+        let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+        for (text, expected) in [
+            ("Rb5", "a5b5"),
+            ("Rfxd1!?", "f1d1"),
+            ("Rf1-f4", "f1f4"),
+            ("Q3b2", "a3b2"),
+            ("Qa1-b2", "a1b2"),
+            ("Qcc2", "c1c2"),
+            ("Kg2", "h1g2"),
+        ] {
+            assert_eq!(uci(&mut state, text).unwrap(), expected, "{text}");
+        }
+        // ambiguous, blocked, no such piece, nonsense, castling and promotion
+        for invalid in ["Qb2", "Rb1", "Nf3", "Rz1", "R", "O-O", "0-0-0", "Qb8=Q"] {
+            assert!(uci(&mut state, invalid).is_err(), "{invalid}");
+        }
+
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+        let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+        for (text, expected) in [
+            ("ed5", "e4d5"),
+            ("exd5", "e4d5"),
+            ("e4xd5", "e4d5"),
+            ("Pe5", "e4e5"),
+            ("Nf3", "g1f3"),
+        ] {
+            assert_eq!(uci(&mut state, text).unwrap(), expected, "{text}");
+        }
+        assert!(uci(&mut state, "e8Q").is_err());
+
+        // every legal move reads back as itself
+        for m in state.legal_moves() {
+            let text = m.to_san(&mut state);
+            assert_eq!(Move::from_san(&text, &mut state).unwrap(), m, "{text}");
+        }
+        assert!(state.history.is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+//! Reading and writing games in Portable Game Notation
+//!
+//! Games are read into and written from a [Game](crate::game::Game), with their comments,
+//! annotation glyphs and variations
+
+pub mod reader;
+pub mod writer;
+
+#[cfg(test)]
+mod test {
+    use super::{reader::read_pgn, writer::LINE_WIDTH};
+    use crate::game::{Game, GameResult, NodeId};
+
+    const PGN: &str = r#"[Event "Casual game"]
+[White "Anderssen"]
+[Black "Kieseritzky"]
+[Result "1-0"]
+
+1. e3 e6 2. Nf3 {the main
+line} Nc6 (2... d6 3. d3 $1 (3. Bc4) 3... e5) 3.Bb5 a6!? 4. Ba4 ; rest of line
+Nf6 1-0
+
+[White "Nobody"]
+
+1. e3 e6 2. Ke3 *
+
+1. d3 d6 1/2-1/2
+[FEN "6k1/5ppp/8/8/8/8/8/3Q2K1 b - - 0 30"]
+30... h6 31. Qd8+ Kh7 *
+"#;
+
+    /// The node `ply` moves along the main line
+    fn main_node(game: &Game, ply: usize) -> NodeId {
+        (0..ply).fold(Game::ROOT, |node, _| game.node(node).children[0])
+    }
+
+    #[test]
+    fn test_pgn() {
+        let games = read_pgn(PGN);
+        assert_eq!(games.len(), 4);
+
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(game.tag("White"), Some("Anderssen"));
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert_eq!(game.main_line().len(), 8);
+        let main = |ply| main_node(game, ply);
+        assert_eq!(game.node(main(3)).comment.as_deref(), Some("the main line"));
+        assert_eq!(game.node(main(6)).nags, vec![5]);
+        assert_eq!(game.node(main(7)).comment.as_deref(), Some("rest of line"));
+
+        // the variation instead of 2... Nc6, with one inside it
+        let variation = game.node(main(3)).children[1];
+        let d3 = game.node(variation).children[0];
+        assert_eq!(game.node(d3).nags, vec![1]);
+        let bc4 = game.node(variation).children[1];
+        assert!(game.node(bc4).children.is_empty());
+        assert_eq!(game.node(bc4).ply, 5);
+
+        // the pawn is in the king's way, and the games after it are still read
+        let error = format!("{:#}", games[1].as_ref().unwrap_err());
+        assert!(error.starts_with("game 2: line 12, move 2. Ke3"), "{error}");
+        assert_eq!(games[2].as_ref().unwrap().result, GameResult::Draw);
+        let game = games[3].as_ref().unwrap();
+        assert_eq!(game.first_move, 30);
+        assert_eq!(game.main_line().len(), 3);
+
+        // writing a game and reading it back gives the same game
+        for game in games.iter().flatten() {
+            let text = game.to_string();
+            assert!(text.lines().all(|line| line.len() <= LINE_WIDTH), "{text}");
+            let read = read_pgn(&text).pop().unwrap().unwrap();
+            assert_eq!(read.tags, game.tags);
+            assert_eq!(read.main_line(), game.main_line());
+            assert_eq!(read.result, game.result);
+            assert_eq!(read.to_string(), text);
+        }
+        let text = games[0].as_ref().unwrap().to_string().replace('\n', " ");
+        assert!(
+            text.contains("{the main line} 2... Nc6 (2... d6 3. d3 $1 (3. Bc4) 3... e5) 3. Bb5")
+        );
+        assert!(games[3]
+            .as_ref()
+            .unwrap()
+            .to_string()
+            .ends_with("30... h6 31. Qd8+ Kh7 *\n"));
+    }
+}
@@ -0,0 +1,361 @@
+use std::{iter::Peekable, str::CharIndices};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    chess::Team,
+    game::{Game, GameResult, NodeId},
+    move_gen::moves::Move,
+    state::State,
+};
+
+/// Reads every game in a PGN file
+///
+/// Each game is read on its own, so one that can't be read is an error in its place
+/// and the games after it are still read
+pub fn read_pgn(text: &str) -> Vec<Result<Game>> {
+    split_games(text)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (line, text))| read_game(text, line).with_context(|| format!("game {}", i + 1)))
+        .collect()
+}
+
+/// Reads a single game, `line` is the line of the file it starts on
+pub fn read_game(text: &str, line: usize) -> Result<Game> {
+    let tokens = Lexer::new(text, line).collect::<Result<Vec<_>>>()?;
+    let mut tokens = tokens.into_iter().peekable();
+
+    let mut tags = Vec::new();
+    while let Some((_, Token::Tag(name, value))) = tokens.peek() {
+        tags.push((name.clone(), value.clone()));
+        tokens.next();
+    }
+    let mut game = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => Game::from_fen(fen).with_context(|| format!("invalid FEN {fen}"))?,
+        None => Game::default(),
+    };
+    game.tags = tags;
+
+    let mut parser = Parser {
+        tokens,
+        node: Game::ROOT,
+        state: game.start().clone(),
+    };
+    parser.moves(&mut game, false)?;
+    game.result = match parser.tokens.next() {
+        Some((_, Token::Result(result))) => result,
+        Some((line, token)) => bail!("line {line}: unexpected {token:?}"),
+        // the result at the end is missing, the tag is better than nothing
+        None => game
+            .tag("Result")
+            .and_then(|result| result.parse().ok())
+            .unwrap_or_default(),
+    };
+    if let Some((line, token)) = parser.tokens.next() {
+        bail!("line {line}: unexpected {token:?} after the result");
+    }
+    Ok(game)
+}
+
+/// Splits a file into the text of each game, with the line each one starts on
+///
+/// A game ends where the tags of the next one begin, or at its result if the next one
+/// has no tags
+fn split_games(text: &str) -> Vec<(usize, &str)> {
+    let mut games = Vec::new();
+    let mut start = 0;
+    let mut start_line = 1;
+    let mut offset = 0;
+    let mut in_comment = false;
+    let mut has_moves = false;
+    let mut ended = false;
+
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            offset += line.len();
+            continue;
+        }
+        let is_tag = !in_comment && trimmed.starts_with('[');
+        if (is_tag && has_moves) || ended {
+            games.push((start_line, &text[start..offset]));
+            start = offset;
+            start_line = i + 1;
+            has_moves = false;
+        }
+        offset += line.len();
+        ended = false;
+        if is_tag || trimmed.starts_with('%') {
+            continue;
+        }
+
+        for ch in trimmed.chars() {
+            match ch {
+                '}' if in_comment => in_comment = false,
+                _ if in_comment => {}
+                '{' => in_comment = true,
+                ';' => break,
+                _ => has_moves = true,
+            }
+        }
+        ended = !in_comment
+            && ["1-0", "0-1", "1/2-1/2", "*"]
+                .iter()
+                .any(|result| trimmed.ends_with(result));
+    }
+    if !text[start..].trim().is_empty() {
+        games.push((start_line, &text[start..]));
+    }
+    games
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Open,
+    Close,
+    Nag(u8),
+    San(String),
+    Result(GameResult),
+}
+
+/// The glyphs that can be written straight after a move instead of a NAG
+const SUFFIXES: [(&str, u8); 6] = [
+    ("!", 1),
+    ("?", 2),
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+];
+
+/// Splits the text of a game into tokens, each with the line it's on
+struct Lexer<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    line: usize,
+    /// Tokens that have been read but not returned yet
+    pending: Vec<Token>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str, line: usize) -> Self {
+        Self {
+            text,
+            chars: text.char_indices().peekable(),
+            line,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Takes characters while `f` is true, returning them and any already taken since `start`
+    fn take_while(&mut self, start: usize, f: impl Fn(char) -> bool) -> &'a str {
+        let mut end = self.chars.peek().map_or(self.text.len(), |&(i, _)| i);
+        while let Some(&(i, ch)) = self.chars.peek()
+            && f(ch)
+        {
+            if ch == '\n' {
+                self.line += 1;
+            }
+            end = i + ch.len_utf8();
+            self.chars.next();
+        }
+        &self.text[start..end]
+    }
+
+    fn tag(&mut self, start: usize) -> Result<Token> {
+        let line = self.line;
+        let name = self.take_while(start, |ch| ch.is_alphanumeric() || ch == '_');
+        self.take_while(start, char::is_whitespace);
+        if self.chars.next().map(|(_, ch)| ch) != Some('"') {
+            bail!("line {line}: invalid tag {name}");
+        }
+        let mut value = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '\\')) => match self.chars.next() {
+                    Some((_, ch)) => value.push(ch),
+                    None => break,
+                },
+                Some((_, '"')) => {
+                    self.take_while(start, char::is_whitespace);
+                    if self.chars.next().map(|(_, ch)| ch) == Some(']') {
+                        return Ok(Token::Tag(name.to_string(), value));
+                    }
+                    break;
+                }
+                Some((_, '\n')) | None => break,
+                Some((_, ch)) => value.push(ch),
+            }
+        }
+        bail!("line {line}: invalid tag {name}")
+    }
+
+    /// A move, result, move number or glyph, and the glyphs written after a move
+    fn symbol(&mut self, start: usize) -> Result<Option<Token>> {
+        let line = self.line;
+        let is_symbol = |ch: char| ch.is_ascii_alphanumeric() || "+#=:-/._!?".contains(ch);
+        let symbol = self.take_while(start, is_symbol);
+        match symbol.chars().next() {
+            Some(ch) if is_symbol(ch) => {}
+            ch => bail!("line {line}: unexpected {}", ch.unwrap_or(' ')),
+        }
+        if let Ok(result) = symbol.parse() {
+            return Ok(Some(Token::Result(result)));
+        }
+
+        // move numbers, which can be run into the move after them
+        let san = symbol.trim_start_matches(|ch: char| ch.is_ascii_digit());
+        let san = match san.starts_with('.') || san.is_empty() {
+            true => san.trim_start_matches('.'),
+            false => symbol,
+        };
+        let annotation = san.trim_start_matches(|ch| ch != '!' && ch != '?');
+        let san = &san[..san.len() - annotation.len()];
+        if !annotation.is_empty() {
+            let nag = SUFFIXES
+                .iter()
+                .find(|(suffix, _)| *suffix == annotation)
+                .with_context(|| format!("line {line}: invalid annotation {annotation}"))?;
+            self.pending.push(Token::Nag(nag.1));
+        }
+        Ok(match san.is_empty() {
+            true => self.pending.pop(),
+            false => Some(Token::San(san.to_string())),
+        })
+    }
+
+    fn token(&mut self) -> Option<Result<(usize, Token)>> {
+        loop {
+            if let Some(token) = self.pending.pop() {
+                return Some(Ok((self.line, token)));
+            }
+            let (i, ch) = self.chars.next()?;
+            let line = self.line;
+            let token = match ch {
+                '\n' => {
+                    self.line += 1;
+                    // escaped lines are for other programs
+                    if let Some((_, '%')) = self.chars.peek() {
+                        self.take_while(i, |ch| ch != '\n');
+                    }
+                    continue;
+                }
+                _ if ch.is_whitespace() => continue,
+                '%' if i == 0 => {
+                    self.take_while(i, |ch| ch != '\n');
+                    continue;
+                }
+                '[' => self.tag(i + 1),
+                '{' => {
+                    let comment = self.take_while(i + 1, |ch| ch != '}');
+                    match self.chars.next() {
+                        Some(_) => Ok(Token::Comment(comment.to_string())),
+                        None => Err(anyhow::anyhow!("line {line}: unterminated comment")),
+                    }
+                }
+                ';' => Ok(Token::Comment(
+                    self.take_while(i + 1, |ch| ch != '\n').to_string(),
+                )),
+                '(' => Ok(Token::Open),
+                ')' => Ok(Token::Close),
+                '*' => Ok(Token::Result(GameResult::Unknown)),
+                '$' => {
+                    let nag = self.take_while(i + 1, |ch| ch.is_ascii_digit());
+                    nag.parse()
+                        .map(Token::Nag)
+                        .with_context(|| format!("line {line}: invalid NAG ${nag}"))
+                }
+                _ => match self.symbol(i) {
+                    Ok(Some(token)) => Ok(token),
+                    Ok(None) => continue,
+                    Err(e) => Err(e),
+                },
+            };
+            return Some(token.map(|token| match token {
+                Token::Comment(comment) => {
+                    let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                    (line, Token::Comment(comment))
+                }
+                token => (line, token),
+            }));
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<(usize, Token)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.token()
+    }
+}
+
+/// Turns the tokens of the movetext into moves, checking each one in the position
+struct Parser<I: Iterator<Item = (usize, Token)>> {
+    tokens: Peekable<I>,
+    /// The last move read
+    node: NodeId,
+    /// The position after it
+    state: State,
+}
+
+impl<I: Iterator<Item = (usize, Token)>> Parser<I> {
+    /// Adds the moves of a line to the game until the end of it
+    fn moves(&mut self, game: &mut Game, variation: bool) -> Result<()> {
+        let start = self.node;
+        let mut comment_before: Option<String> = None;
+        while let Some((line, token)) = self
+            .tokens
+            .next_if(|(_, token)| !matches!(token, Token::Close | Token::Result(_)))
+        {
+            let first = self.node == start;
+            match token {
+                Token::San(san) => {
+                    let number = game.move_number(game.node(self.node).ply);
+                    let dots = match self.state.turn {
+                        Team::White => ".",
+                        Team::Black => "...",
+                    };
+                    let m = Move::from_san(&san, &mut self.state)
+                        .with_context(|| format!("line {line}, move {number}{dots} {san}"))?;
+                    let from = self.state.board_state.square_of(m.piece);
+                    self.state.make_move(from, m.to);
+                    self.node = game.add_move(self.node, (from, m.to));
+                    game.node_mut(self.node).comment_before = comment_before.take();
+                }
+                Token::Comment(comment) => {
+                    let target = match first && variation {
+                        true => &mut comment_before,
+                        false => &mut game.node_mut(self.node).comment,
+                    };
+                    *target = Some(match target.take() {
+                        Some(before) => format!("{before} {comment}"),
+                        None => comment,
+                    });
+                }
+                Token::Nag(nag) if !first => game.node_mut(self.node).nags.push(nag),
+                Token::Nag(nag) => bail!("line {line}: ${nag} before the first move"),
+                Token::Open if !first => {
+                    // the variation is played instead of the last move
+                    let (node, state) = (self.node, self.state.clone());
+                    self.node = game.node(node).parent.expect("a move was played");
+                    self.state.unmake_move();
+                    let alternative = self.moves(game, true);
+                    (self.node, self.state) = (node, state);
+                    alternative?;
+                    match self.tokens.next() {
+                        Some((_, Token::Close)) => {}
+                        _ => bail!("line {line}: unterminated variation"),
+                    }
+                }
+                Token::Open => bail!("line {line}: variation before the first move"),
+                Token::Tag(name, _) => bail!("line {line}: tag {name} in the movetext"),
+                Token::Close | Token::Result(_) => unreachable!("stopped before"),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt::Display;
+
+use crate::{
+    chess::Team,
+    game::{Game, NodeId},
+    move_gen::notation::UciMove,
+    state::State,
+};
+
+/// The longest line written, the standard asks for fewer than 80 characters
+pub const LINE_WIDTH: usize = 79;
+
+/// Writes the game as PGN, with the `Result` tag matching the result of the game
+impl Display for Game {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in &self.tags {
+            let value = match name.as_str() {
+                "Result" => self.result.to_string(),
+                _ => value.replace('\\', "\\\\").replace('"', "\\\""),
+            };
+            writeln!(f, "[{name} \"{value}\"]")?;
+        }
+        if !self.tags.is_empty() {
+            writeln!(f)?;
+        }
+
+        let mut writer = Writer { tokens: Vec::new() };
+        let root = self.node(Game::ROOT);
+        if let Some(comment) = &root.comment {
+            writer.comment(comment);
+        }
+        if let Some(&first) = root.children.first() {
+            writer.line(self, &mut self.start().clone(), first);
+        }
+        writer.tokens.push(self.result.to_string());
+
+        let mut line = String::new();
+        for token in writer.tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+                writeln!(f, "{line}")?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{line}")
+    }
+}
+
+/// Turns a game into the tokens of its movetext, which are then wrapped into lines
+struct Writer {
+    tokens: Vec<String>,
+}
+
+impl Writer {
+    /// Comments are split into words so that long ones can be wrapped
+    fn comment(&mut self, comment: &str) {
+        let start = self.tokens.len();
+        self.tokens
+            .extend(comment.split_whitespace().map(String::from));
+        if self.tokens.len() == start {
+            self.tokens.push(String::new());
+        }
+        self.tokens[start].insert(0, '{');
+        self.tokens.last_mut().expect("pushed above").push('}');
+    }
+
+    /// Writes a line starting with the move of `node`, which is played from `state`,
+    /// leaving the state as it was
+    fn line(&mut self, game: &Game, state: &mut State, mut node: NodeId) {
+        if let Some(comment) = &game.node(node).comment_before {
+            self.comment(comment);
+        }
+        // black's move needs its number at the start and after anything in between
+        let mut numbered = false;
+        let mut played = 0;
+        loop {
+            let current = game.node(node);
+            let m = current.m.expect("only the root has no move");
+            let number = game.move_number(current.ply - 1);
+            let san = match UciMove::from(m).resolve(state) {
+                Ok(legal) => legal.to_san(state),
+                // not something a game would have let through, but better than nothing
+                Err(_) => UciMove::from(m).to_string(),
+            };
+            // the number stays on the same line as its move
+            self.tokens.push(match state.turn {
+                Team::White => format!("{number}. {san}"),
+                Team::Black if !numbered => format!("{number}... {san}"),
+                Team::Black => san,
+            });
+            self.tokens
+                .extend(current.nags.iter().map(|nag| format!("${nag}")));
+            numbered = true;
+            if let Some(comment) = &current.comment {
+                self.comment(comment);
+                numbered = false;
+            }
+
+            // the moves that could have been played instead of a main line move
+            let parent = game.node(current.parent.expect("only the root has no parent"));
+            if parent.children[0] == node {
+                for &alternative in &parent.children[1..] {
+                    let start = self.tokens.len();
+                    self.line(game, state, alternative);
+                    self.tokens[start].insert(0, '(');
+                    self.tokens.last_mut().expect("not empty").push(')');
+                    numbered = false;
+                }
+            }
+
+            state.make_move(m.0, m.1);
+            played += 1;
+            match current.children.first() {
+                Some(&child) => node = child,
+                None => break,
+            }
+        }
+        for _ in 0..played {
+            state.unmake_move();
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use std::{
+    io::{BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    chess::Team,
+    eval::nnue::Network,
+    move_gen::notation::UciMove,
+    search::{
+        alphabeta::{AlphaBeta, SquareMove},
+        limits::{time_for_move, Progress, SearchLimits},
+        tt::TranspositionTable,
+        Eval,
+    },
+    state::State,
+};
+
+use super::{format_move, hash_bits, load_network, load_position, send};
+
+/// The transposition table size in megabytes
+const HASH: usize = 16;
+
+/// How the clock works, set by `level` or `st`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TimeControl {
+    /// `moves` moves in `base`, or the whole game if `moves` is 0, gaining `increment` every move
+    Conventional {
+        moves: u32,
+        base: Duration,
+        increment: Duration,
+    },
+    /// Exactly this long for every move
+    PerMove(Duration),
+}
+
+impl Default for TimeControl {
+    /// 40 moves in 5 minutes, until the GUI says otherwise
+    fn default() -> Self {
+        TimeControl::Conventional {
+            moves: 40,
+            base: Duration::from_secs(300),
+            increment: Duration::ZERO,
+        }
+    }
+}
+
+/// A Chess Engine Communication Protocol (XBoard) front end
+///
+/// Like [Uci](super::uci::Uci) the thinking happens on another thread, so the GUI can
+/// still tell the engine to move now, or to stop thinking and forget about it
+pub struct Cecp<W: Write + Send + 'static> {
+    out: Arc<Mutex<W>>,
+    state: State,
+    /// `None` while the engine is thinking with it
+    search: Option<AlphaBeta>,
+    /// Hands back the search and the move it played, if it got to play one
+    running: Option<JoinHandle<(AlphaBeta, Option<SquareMove>)>>,
+    /// The limits of the running search, to make it move now
+    limits: SearchLimits,
+    /// Set to throw away the move being thought about
+    abandon: Arc<AtomicBool>,
+    /// The side the engine plays, `None` in force mode
+    engine: Option<Team>,
+    time_control: TimeControl,
+    /// What's left on the engine's clock, from `time`
+    clock: Option<Duration>,
+    depth: Option<i32>,
+    /// Whether to show the thinking
+    post: Arc<AtomicBool>,
+    /// The network from the `EvalFile` option, if there is one
+    network: Option<Arc<Network>>,
+}
+
+impl<W: Write + Send + 'static> Cecp<W> {
+    pub fn new(out: W) -> Self {
+        let out = Arc::new(Mutex::new(out));
+        let post = Arc::new(AtomicBool::new(false));
+        let mut search = AlphaBeta::default();
+        search.tt = Arc::new(TranspositionTable::new(hash_bits(HASH)));
+        let (info, show) = (out.clone(), post.clone());
+        search.on_progress(move |progress| {
+            if progress.multi_pv == 1 && show.load(Ordering::Relaxed) {
+                let _ = send(&info, &format_thinking(progress));
+            }
+        });
+
+        Self {
+            out,
+            state: load_position(None, [], None).expect("the starting position is valid"),
+            search: Some(search),
+            running: None,
+            limits: SearchLimits::default(),
+            abandon: Arc::new(AtomicBool::new(false)),
+            engine: Some(Team::Black),
+            time_control: TimeControl::default(),
+            clock: None,
+            depth: None,
+            post,
+            network: None,
+        }
+    }
+
+    /// Answers commands until `quit` or the end of the input,
+    /// waiting for the engine to finish thinking in the second case
+    pub fn run(&mut self, input: impl BufRead) -> Result<()> {
+        for line in input.lines() {
+            if !self.command(&line?)? {
+                return Ok(());
+            }
+        }
+        self.finish();
+        Ok(())
+    }
+
+    /// Answers one command, returning false once it's time to quit
+    ///
+    /// Mistakes in commands are reported to the GUI, only failing to write the reply is an error
+    pub fn command(&mut self, line: &str) -> Result<bool> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else { return Ok(true) };
+        let argument = line.trim()[command.len()..].trim();
+
+        // anything that changes the game waits for the engine to finish thinking first
+        let result = match command {
+            "?" => {
+                self.limits.stop();
+                Ok(())
+            }
+            "ping" => self.send(&format!("pong {argument}")),
+            "quit" => {
+                self.abandon();
+                return Ok(false);
+            }
+            // nothing to do about these
+            "xboard" | "accepted" | "rejected" | "random" | "computer" | "name" | "rating"
+            | "otim" | "easy" | "hard" => Ok(()),
+            "post" | "nopost" => {
+                self.post.store(command == "post", Ordering::Relaxed);
+                Ok(())
+            }
+            "time" => argument
+                .parse::<u64>()
+                .context("invalid time")
+                .map(|centiseconds| {
+                    self.clock = Some(Duration::from_millis(10 * centiseconds));
+                }),
+            "protover" => self.send(concat!(
+                "feature ping=1 setboard=1 usermove=1 time=1 draw=0 sigint=0 sigterm=0 ",
+                "reuse=1 analyze=0 colors=0 myname=\"Oxide Gambit\" variants=\"normal\" ",
+                "option=\"EvalFile -file \" done=1"
+            )),
+            "new" => {
+                self.abandon();
+                self.state = load_position(None, [], self.network.clone())
+                    .expect("the starting position is valid");
+                self.engine = Some(Team::Black);
+                self.time_control = TimeControl::default();
+                self.clock = None;
+                self.depth = None;
+                self.search_mut().clear();
+                Ok(())
+            }
+            "setboard" => {
+                self.abandon();
+                match load_position(Some(argument), [], self.network.clone()) {
+                    Ok(state) => {
+                        self.state = state;
+                        Ok(())
+                    }
+                    Err(error) => Err(error.context(format!("Error (bad FEN): {argument}"))),
+                }
+            }
+            "usermove" => {
+                self.finish();
+                self.user_move(argument)
+            }
+            "go" => {
+                self.finish();
+                self.engine = Some(self.state.turn);
+                self.think()
+            }
+            "force" | "result" => {
+                self.abandon();
+                self.engine = None;
+                Ok(())
+            }
+            "undo" | "remove" => {
+                self.abandon();
+                let moves = if command == "undo" { 1 } else { 2 };
+                for _ in 0..moves {
+                    self.state.unmake_move();
+                }
+                Ok(())
+            }
+            "level" => {
+                self.finish();
+                self.level(argument)
+            }
+            "st" => {
+                self.finish();
+                argument.parse().context("invalid st").map(|seconds| {
+                    self.time_control = TimeControl::PerMove(Duration::from_secs(seconds));
+                })
+            }
+            "option" => {
+                self.finish();
+                self.option(argument)
+            }
+            "sd" => {
+                self.finish();
+                argument.parse().context("invalid sd").map(|depth| {
+                    self.depth = Some(depth);
+                })
+            }
+            _ => Err(anyhow::anyhow!("Error (unknown command): {command}")),
+        };
+        if let Err(error) = result {
+            // the context is already in the form the GUI expects
+            let message = error.to_string();
+            match message.starts_with("Error") || message.starts_with("Illegal move") {
+                true => self.send(&message)?,
+                false => self.send(&format!("Error ({error:#}): {line}"))?,
+            }
+        }
+        Ok(true)
+    }
+
+    /// Plays the opponent's move, and answers it if it's the engine's turn
+    fn user_move(&mut self, text: &str) -> Result<()> {
+        let m = match text.parse::<UciMove>() {
+            Ok(m) if m.resolve(&mut self.state).is_ok() => m,
+            _ => bail!("Illegal move: {text}"),
+        };
+        self.state.make_move(m.from, m.to);
+        match self.engine == Some(self.state.turn) {
+            true => self.think(),
+            false => Ok(()),
+        }
+    }
+
+    /// `level <moves> <minutes>[:<seconds>] <increment>`
+    fn level(&mut self, argument: &str) -> Result<()> {
+        let &[moves, base, increment] = argument.split_whitespace().collect::<Vec<_>>().as_slice()
+        else {
+            bail!("level needs moves, base and increment");
+        };
+        let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+        let base = 60 * minutes.parse::<u64>()? + seconds.parse::<u64>()?;
+        let increment = (increment.parse::<f64>()? * 1000.0) as u64;
+        self.time_control = TimeControl::Conventional {
+            moves: moves.parse()?,
+            base: Duration::from_secs(base),
+            increment: Duration::from_millis(increment),
+        };
+        Ok(())
+    }
+
+    /// `option <name>=<value>`, for the options sent with the features
+    fn option(&mut self, argument: &str) -> Result<()> {
+        match argument.split_once('=') {
+            Some(("EvalFile", path)) => {
+                self.network = load_network(path)?;
+                self.state.set_network(self.network.clone());
+                Ok(())
+            }
+            _ => bail!("unknown option"),
+        }
+    }
+
+    /// Starts thinking about a move for the side to move, playing it when done
+    fn think(&mut self) -> Result<()> {
+        let mut limits = SearchLimits::default();
+        limits.depth = self.depth;
+        limits.movetime = Some(match self.time_control {
+            TimeControl::PerMove(time) => time,
+            TimeControl::Conventional {
+                moves,
+                base,
+                increment,
+            } => {
+                // the moves already made count towards the time control
+                let moves_to_go = match moves {
+                    0 => None,
+                    moves => Some(moves - (self.state.history.len() as u32 / 2) % moves),
+                };
+                time_for_move(self.clock.unwrap_or(base), increment, moves_to_go)
+            }
+        });
+
+        let mut search = self.search.take().expect("the engine isn't thinking");
+        let mut state = self.state.clone();
+        let (out, abandon) = (self.out.clone(), self.abandon.clone());
+        abandon.store(false, Ordering::Relaxed);
+        self.limits = limits.clone();
+        self.running = Some(std::thread::spawn(move || {
+            let result = search.search(&mut state, &limits);
+            // hold on to the output, so the move can't be abandoned halfway through sending it
+            let mut out = out.lock().expect("writing doesn't panic");
+            if abandon.load(Ordering::Relaxed) {
+                return (search, None);
+            }
+            let reply = match result.pv.first() {
+                Some(&m) => format!("move {}", format_move(Some(m))),
+                None if state.in_check(state.turn) => match state.turn {
+                    Team::White => "0-1 {Black mates}".to_string(),
+                    Team::Black => "1-0 {White mates}".to_string(),
+                },
+                None => "1/2-1/2 {Stalemate}".to_string(),
+            };
+            let _ = writeln!(out, "{reply}").and_then(|_| out.flush());
+            (search, result.pv.first().copied())
+        }));
+        Ok(())
+    }
+
+    /// Waits for the engine to finish thinking, and plays its move
+    fn finish(&mut self) {
+        if let Some(running) = self.running.take() {
+            let (search, m) = running.join().expect("the search thread doesn't panic");
+            self.search = Some(search);
+            if let Some((from, to)) = m {
+                self.state.make_move(from, to);
+            }
+        }
+    }
+
+    /// Stops the engine thinking without playing a move, unless it has already sent one
+    fn abandon(&mut self) {
+        {
+            let _out = self.out.lock().expect("writing doesn't panic");
+            self.abandon.store(true, Ordering::Relaxed);
+        }
+        self.limits.stop();
+        self.finish();
+    }
+
+    fn search_mut(&mut self) -> &mut AlphaBeta {
+        self.search.as_mut().expect("the engine isn't thinking")
+    }
+
+    fn send(&self, line: &str) -> Result<()> {
+        send(&self.out, line)
+    }
+}
+
+/// `<depth> <score> <time> <nodes> <pv>`, with the time in centiseconds
+/// and mates as 100000 plus the number of moves
+fn format_thinking(progress: &Progress) -> String {
+    let score = match progress.eval() {
+        Eval::Centipawns(cp) => cp,
+        Eval::Mate(moves) if moves > 0 => 100_000 + moves,
+        Eval::Mate(moves) => -100_000 + moves,
+    };
+    let pv = progress
+        .pv
+        .iter()
+        .map(|&m| format_move(Some(m)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "{} {score} {} {} {pv}",
+        progress.depth,
+        progress.time.as_millis() / 10,
+        progress.nodes,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::test::Output;
+
+    fn run(input: &str) -> Vec<String> {
+        let output = Output::default();
+        Cecp::new(output.clone()).run(input.as_bytes()).unwrap();
+        output.lines()
+    }
+
+    #[test]
+    fn test_cecp() {
+        let lines = run("xboard\nprotover 2\nping 1\n");
+        assert!(lines[0].starts_with("feature ") && lines[0].ends_with("done=1"));
+        assert_eq!(lines[1], "pong 1");
+
+        // the engine plays black by default, and answers straight away
+        let lines = run("new\npost\nsd 2\nusermove e2e3\n");
+        assert!(lines.iter().any(|l| l.starts_with("2 ")));
+        assert!(lines.last().unwrap().starts_with("move "));
+
+        // in force mode it just keeps track of the moves
+        let lines = run(
+            "new\nforce\nusermove e2e3\nusermove e7e6\nusermove e2e5\nundo\nusermove d7d6\nsd 2\ngo\n",
+        );
+        assert_eq!(lines[0], "Illegal move: e2e5");
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("move "));
+
+        // the queen takes the rook
+        let lines = run("setboard 4k3/8/8/3r4/8/8/8/3QK3 w - - 0 1\nst 1\nsd 3\ngo\n");
+        assert_eq!(lines, ["move d1d5"]);
+
+        let lines = run("foo\nsd x\nsetboard k7/8/8/8/8/8/5q2/7K w - - 0 1\ngo\n");
+        assert_eq!(lines[0], "Error (unknown command): foo");
+        assert!(lines[1].starts_with("Error (invalid sd: "));
+        assert_eq!(lines[2], "1/2-1/2 {Stalemate}");
+    }
+
+    #[test]
+    fn test_new() {
+        // a new game starts with a fresh clock and time control
+        let mut cecp = Cecp::new(Output::default());
+        for command in ["st 10", "time 500", "sd 3", "new"] {
+            cecp.command(command).unwrap();
+        }
+        assert_eq!(cecp.time_control, TimeControl::default());
+        assert_eq!(cecp.clock, None);
+        assert_eq!(cecp.depth, None);
+    }
+
+    #[test]
+    fn test_option() {
+        let path = std::env::temp_dir().join("oxide-gambit-cecp.nnue");
+        Network::zeroed(32, 8).save(&path).unwrap();
+        let mut cecp = Cecp::new(Output::default());
+        cecp.command(&format!("option EvalFile={}", path.display()))
+            .unwrap();
+        cecp.command("new").unwrap();
+        assert!(cecp.state.nnue.is_some());
+        cecp.command("option EvalFile=").unwrap();
+        assert!(cecp.state.nnue.is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_move_now() {
+        let output = Output::default();
+        let mut cecp = Cecp::new(output.clone());
+        cecp.command("st 1000").unwrap();
+        cecp.command("go").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        cecp.command("?").unwrap();
+        cecp.command("ping 2").unwrap();
+        cecp.run("".as_bytes()).unwrap();
+        let lines = output.lines();
+        assert!(lines.iter().any(|l| l.starts_with("move ")));
+
+        // the engine's move was played, so it's black's turn
+        assert_eq!(cecp.state.turn, Team::Black);
+        cecp.command("go").unwrap();
+        cecp.command("force").unwrap();
+        assert_eq!(
+            cecp.state.history.len() % 2,
+            1,
+            "an abandoned move isn't played"
+        );
+    }
+}
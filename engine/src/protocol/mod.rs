@@ -0,0 +1,113 @@
+pub mod cecp;
+pub mod uci;
+
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+
+use crate::{
+    eval::nnue::Network,
+    move_gen::notation::UciMove,
+    rules::{fen::START_FEN, Rules},
+    search::{alphabeta::SquareMove, tt::TranspositionTable},
+    state::State,
+};
+
+/// Writes a move in coordinate notation, `0000` for no move at all
+pub fn format_move(m: Option<SquareMove>) -> String {
+    match m {
+        Some(m) => UciMove::from(m).to_string(),
+        None => "0000".to_string(),
+    }
+}
+
+/// Writes a line and sends it straight away
+fn send<W: Write>(out: &Mutex<W>, line: &str) -> Result<()> {
+    let mut out = out.lock().expect("writing doesn't panic");
+    writeln!(out, "{line}")?;
+    out.flush()?;
+    Ok(())
+}
+
+/// The most bits of transposition table index that fit in `mb` megabytes
+fn hash_bits(mb: usize) -> u32 {
+    let entries = (mb << 20) / TranspositionTable::ENTRY_SIZE;
+    entries.max(1).ilog2()
+}
+
+/// Loads the network for the `EvalFile` option, nothing or `<empty>` for the handcrafted evaluation
+fn load_network(path: &str) -> Result<Option<Arc<Network>>> {
+    match path {
+        "" | "<empty>" => Ok(None),
+        path => Ok(Some(Arc::new(Network::load(path)?))),
+    }
+}
+
+/// Sets up the position from a FEN, or the starting position, and plays the moves after it
+///
+/// The position is evaluated with `network` if there is one
+pub fn load_position<'a>(
+    fen: Option<&str>,
+    moves: impl IntoIterator<Item = &'a str>,
+    network: Option<Arc<Network>>,
+) -> Result<State> {
+    let mut state = State::from_FEN(fen.unwrap_or(START_FEN), Rules::standard())?;
+    state.set_network(network);
+    for text in moves {
+        let m = text.parse::<UciMove>()?;
+        m.resolve(&mut state)?;
+        state.make_move(m.from, m.to);
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Output that can be read back after the front end is done with it
+    #[derive(Clone, Default)]
+    pub struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        pub fn lines(&self) -> Vec<String> {
+            let bytes = self.0.lock().unwrap();
+            String::from_utf8_lossy(&bytes)
+                .lines()
+                .map(String::from)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_load_position() {
+        let state = load_position(None, ["e2e3", "e7e6", "d1g4"], None).unwrap();
+        assert_eq!(state.history.len(), 3);
+        assert!(state.nnue.is_none());
+        // black pieces can't move on white's turn
+        assert!(load_position(None, ["d7d6"], None).is_err());
+        assert!(load_position(Some("8/8 w"), [], None).is_err());
+
+        let network = Some(Arc::new(Network::zeroed(32, 8)));
+        let state = load_position(None, ["e2e3"], network).unwrap();
+        assert!(state.nnue.is_some());
+        assert_eq!(
+            format_move(Some(("e2".parse().unwrap(), "e3".parse().unwrap()))),
+            "e2e3"
+        );
+        assert_eq!(format_move(None), "0000");
+    }
+}
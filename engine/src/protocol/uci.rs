@@ -0,0 +1,468 @@
+use std::{
+    io::{BufRead, Write},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    chess::Team,
+    eval::nnue::Network,
+    search::{
+        alphabeta::AlphaBeta,
+        bench::{bench, BENCH_DEPTH},
+        limits::{time_for_move, Progress, SearchLimits},
+        skill::Skill,
+        tt::TranspositionTable,
+        Eval,
+    },
+    state::State,
+};
+
+use super::{format_move, hash_bits, load_network, load_position, send};
+
+/// The transposition table size in megabytes, to start with and at most
+const DEFAULT_HASH: usize = 16;
+const MAX_HASH: usize = 4096;
+const MAX_THREADS: usize = 256;
+const MAX_MULTI_PV: usize = 64;
+
+/// Everything `go` can be followed by, which ends a `searchmoves` list
+const GO_PARAMETERS: [&str; 12] = [
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+];
+
+/// A Universal Chess Interface front end
+///
+/// Commands are read a line at a time and the replies written to `out`. Searches run on
+/// another thread, so `stop`, `ponderhit` and `isready` are answered while they're going
+pub struct Uci<W: Write + Send + 'static> {
+    out: Arc<Mutex<W>>,
+    state: State,
+    /// `None` while a search has it
+    search: Option<AlphaBeta>,
+    running: Option<JoinHandle<AlphaBeta>>,
+    /// The limits of the running search, to stop it or tell it about a ponder hit
+    limits: SearchLimits,
+    skill_level: u8,
+    limit_strength: bool,
+    elo: u32,
+    /// The network from the `EvalFile` option, if there is one
+    network: Option<Arc<Network>>,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    pub fn new(out: W) -> Self {
+        let out = Arc::new(Mutex::new(out));
+        let mut search = AlphaBeta::default();
+        search.tt = Arc::new(TranspositionTable::new(hash_bits(DEFAULT_HASH)));
+        let info = out.clone();
+        search.on_progress(move |progress| {
+            // the GUI going away is noticed when reading the next command
+            let _ = send(&info, &format_info(progress));
+        });
+
+        Self {
+            out,
+            state: load_position(None, [], None).expect("the starting position is valid"),
+            search: Some(search),
+            running: None,
+            limits: SearchLimits::default(),
+            skill_level: Skill::MAX_LEVEL,
+            limit_strength: false,
+            elo: Skill::MAX_ELO,
+            network: None,
+        }
+    }
+
+    /// Answers commands until `quit` or the end of the input,
+    /// waiting for the last search to finish in the second case
+    pub fn run(&mut self, input: impl BufRead) -> Result<()> {
+        for line in input.lines() {
+            if !self.command(&line?)? {
+                return Ok(());
+            }
+        }
+        self.wait();
+        Ok(())
+    }
+
+    /// Answers one command, returning false once it's time to quit
+    ///
+    /// Mistakes in commands are reported to the GUI as `info string`, only failing
+    /// to write the reply is an error
+    pub fn command(&mut self, line: &str) -> Result<bool> {
+        let mut words = line.split_whitespace();
+        let result = match words.next() {
+            Some("uci") => self.uci(),
+            Some("isready") => self.send("readyok"),
+            Some("ucinewgame") => {
+                self.stop();
+                self.search_mut().clear();
+                Ok(())
+            }
+            Some("position") => {
+                self.stop();
+                self.position(words)
+            }
+            Some("go") => {
+                self.stop();
+                self.go(words)
+            }
+            Some("stop") => {
+                self.stop();
+                Ok(())
+            }
+            Some("ponderhit") => {
+                self.limits.ponder_hit();
+                Ok(())
+            }
+            Some("setoption") => {
+                self.stop();
+                self.set_option(line)
+            }
+            Some("quit") => {
+                self.stop();
+                return Ok(false);
+            }
+            Some("bench") => {
+                self.stop();
+                self.bench(words.next())
+            }
+            // GUIs send these to every engine, there's nothing to do about them
+            Some("debug" | "register") | None => Ok(()),
+            Some(command) => Err(anyhow::anyhow!("unknown command {command}")),
+        };
+        if let Err(error) = result {
+            self.send(&format!("info string {error:#}"))?;
+        }
+        Ok(true)
+    }
+
+    /// Searches the bench positions, not part of the protocol but handy from a terminal
+    fn bench(&mut self, depth: Option<&str>) -> Result<()> {
+        let depth = match depth {
+            Some(depth) => depth.parse().context("invalid depth")?,
+            None => BENCH_DEPTH,
+        };
+        let result = bench(depth)?;
+        for line in result.to_string().lines() {
+            self.send(line)?;
+        }
+        Ok(())
+    }
+
+    fn uci(&mut self) -> Result<()> {
+        self.send("id name Oxide Gambit")?;
+        self.send("id author the Oxide Gambit developers")?;
+        self.send(&format!(
+            "option name Hash type spin default {DEFAULT_HASH} min 1 max {MAX_HASH}"
+        ))?;
+        self.send(&format!(
+            "option name Threads type spin default 1 min 1 max {MAX_THREADS}"
+        ))?;
+        self.send(&format!(
+            "option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}"
+        ))?;
+        self.send("option name Ponder type check default false")?;
+        self.send(&format!(
+            "option name Skill Level type spin default {0} min 0 max {0}",
+            Skill::MAX_LEVEL
+        ))?;
+        self.send("option name UCI_LimitStrength type check default false")?;
+        self.send(&format!(
+            "option name UCI_Elo type spin default {} min {} max {}",
+            Skill::MAX_ELO,
+            Skill::MIN_ELO,
+            Skill::MAX_ELO
+        ))?;
+        self.send("option name EvalFile type string default <empty>")?;
+        self.send("uciok")
+    }
+
+    /// `position [startpos | fen <fen>] [moves <move>...]`
+    fn position<'a>(&mut self, mut words: impl Iterator<Item = &'a str>) -> Result<()> {
+        let fen = match words.next() {
+            Some("startpos") => None,
+            Some("fen") => {
+                let mut fields = Vec::new();
+                for word in words.by_ref() {
+                    if word == "moves" {
+                        break;
+                    }
+                    fields.push(word);
+                }
+                // the clocks are often left off
+                match fields.len() {
+                    4 => fields.extend(["0", "1"]),
+                    5 => fields.push("1"),
+                    _ => {}
+                }
+                Some(fields.join(" "))
+            }
+            _ => bail!("position needs startpos or fen"),
+        };
+        let moves = words.skip_while(|&word| word == "moves");
+        self.state = load_position(fen.as_deref(), moves, self.network.clone())?;
+        Ok(())
+    }
+
+    /// `go` with any of the usual limits, searching on another thread until it's done
+    ///
+    /// The GUI waits for a best move whatever it sent, so mistakes are only reported and
+    /// parameters that aren't known are skipped, as the spec asks
+    fn go<'a>(&mut self, words: impl Iterator<Item = &'a str>) -> Result<()> {
+        let mut words = words.peekable();
+        let mut limits = SearchLimits::default();
+        let (mut time, mut increment, mut moves_to_go) = (None, Duration::ZERO, None);
+        let turn = self.state.turn;
+        while let Some(word) = words.next() {
+            let mut value = || -> Result<u64> {
+                let value = words
+                    .next()
+                    .with_context(|| format!("{word} needs a value"))?;
+                value
+                    .parse()
+                    .with_context(|| format!("invalid {word} {value}"))
+            };
+            let ms = Duration::from_millis;
+            let parsed = match word {
+                "wtime" if turn == Team::White => value().map(|v| time = Some(ms(v))),
+                "btime" if turn == Team::Black => value().map(|v| time = Some(ms(v))),
+                "winc" if turn == Team::White => value().map(|v| increment = ms(v)),
+                "binc" if turn == Team::Black => value().map(|v| increment = ms(v)),
+                // the opponent's clock
+                "wtime" | "btime" | "winc" | "binc" => value().map(|_| ()),
+                "movestogo" => value().map(|v| moves_to_go = Some(v as u32)),
+                "depth" => value().map(|v| limits.depth = Some(v as i32)),
+                "nodes" => value().map(|v| limits.nodes = Some(v)),
+                // enough to see a mate in that many moves
+                "mate" => value().map(|v| limits.depth = Some(2 * v as i32 - 1)),
+                "movetime" => value().map(|v| limits.movetime = Some(ms(v))),
+                "infinite" => {
+                    limits.infinite = true;
+                    Ok(())
+                }
+                "ponder" => {
+                    limits = limits.ponder();
+                    Ok(())
+                }
+                // every move is searched, the list runs until the next parameter
+                "searchmoves" => {
+                    while words.next_if(|w| !GO_PARAMETERS.contains(w)).is_some() {}
+                    Ok(())
+                }
+                _ => Ok(()),
+            };
+            if let Err(error) = parsed {
+                self.send(&format!("info string {error:#}"))?;
+            }
+        }
+        if limits.movetime.is_none()
+            && let Some(time) = time
+        {
+            limits.movetime = Some(time_for_move(time, increment, moves_to_go));
+        }
+
+        let mut search = self.search.take().expect("no search is running");
+        let mut state = self.state.clone();
+        let out = self.out.clone();
+        self.limits = limits.clone();
+        self.running = Some(std::thread::spawn(move || {
+            let result = search.search(&mut state, &limits);
+            let mut reply = format!("bestmove {}", format_move(result.pv.first().copied()));
+            if let Some(ponder) = result.ponder_move() {
+                reply += &format!(" ponder {}", format_move(Some(ponder)));
+            }
+            let _ = send(&out, &reply);
+            search
+        }));
+        Ok(())
+    }
+
+    /// `setoption name <name> [value <value>]`, the name can have spaces in it
+    fn set_option(&mut self, line: &str) -> Result<()> {
+        let rest = line
+            .split_once(" name ")
+            .context("setoption needs a name")?
+            .1;
+        let (name, value) = match rest.split_once(" value ") {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (rest.trim(), ""),
+        };
+        let number = || -> Result<usize> {
+            value
+                .parse()
+                .with_context(|| format!("invalid value {value} for {name}"))
+        };
+        let check = || -> Result<bool> {
+            value
+                .parse()
+                .with_context(|| format!("invalid value {value} for {name}"))
+        };
+
+        match name.to_lowercase().as_str() {
+            "hash" => {
+                let bits = hash_bits(number()?.clamp(1, MAX_HASH));
+                self.search_mut().tt = Arc::new(TranspositionTable::new(bits));
+            }
+            "threads" => self.search_mut().options.threads = number()?.clamp(1, MAX_THREADS),
+            "multipv" => self.search_mut().options.multi_pv = number()?.clamp(1, MAX_MULTI_PV),
+            // pondering is up to the GUI, the search just has to support it
+            "ponder" => {
+                check()?;
+            }
+            "skill level" => self.skill_level = number()?.min(Skill::MAX_LEVEL as usize) as u8,
+            "uci_limitstrength" => self.limit_strength = check()?,
+            "uci_elo" => self.elo = number()? as u32,
+            "evalfile" => {
+                self.network = load_network(value)?;
+                self.state.set_network(self.network.clone());
+            }
+            _ => bail!("unknown option {name}"),
+        }
+
+        let skill = match self.limit_strength {
+            true => Skill::from_elo(self.elo),
+            false => Skill::new(self.skill_level),
+        };
+        self.search_mut().options.skill = skill;
+        Ok(())
+    }
+
+    /// Stops the running search, if there is one, once it has sent its best move
+    fn stop(&mut self) {
+        self.limits.stop();
+        self.wait();
+    }
+
+    /// Waits for the running search to finish by itself
+    fn wait(&mut self) {
+        if let Some(running) = self.running.take() {
+            self.search = Some(running.join().expect("the search thread doesn't panic"));
+        }
+    }
+
+    fn search_mut(&mut self) -> &mut AlphaBeta {
+        self.search.as_mut().expect("no search is running")
+    }
+
+    fn send(&self, line: &str) -> Result<()> {
+        send(&self.out, line)
+    }
+}
+
+fn format_info(progress: &Progress) -> String {
+    let score = match progress.eval() {
+        Eval::Centipawns(cp) => format!("cp {cp}"),
+        Eval::Mate(moves) => format!("mate {moves}"),
+    };
+    let pv = progress
+        .pv
+        .iter()
+        .map(|&m| format_move(Some(m)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "info depth {} seldepth {} multipv {} score {score} nodes {} nps {} hashfull {} time {} pv {pv}",
+        progress.depth,
+        progress.seldepth,
+        progress.multi_pv,
+        progress.nodes,
+        progress.nps,
+        progress.hashfull,
+        progress.time.as_millis(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::protocol::test::Output;
+
+    fn run(input: &str) -> Vec<String> {
+        let output = Output::default();
+        Uci::new(output.clone()).run(input.as_bytes()).unwrap();
+        output.lines()
+    }
+
+    #[test]
+    fn test_uci() {
+        let lines = run("uci\nisready\n");
+        assert_eq!(lines[0], "id name Oxide Gambit");
+        assert!(lines
+            .iter()
+            .any(|l| l.starts_with("option name Hash type spin")));
+        assert_eq!(lines[lines.len() - 2..], ["uciok", "readyok"]);
+
+        let lines = run("position startpos moves e2e3 e7e6\ngo depth 3\n");
+        assert!(lines.iter().any(|l| l.starts_with("info depth 3 ")));
+        let bestmove = lines.last().unwrap();
+        assert!(bestmove.starts_with("bestmove "), "{bestmove}");
+
+        // the queen takes the rook that's hanging
+        let lines = run("setoption name MultiPV value 2\nposition fen 4k3/8/8/3r4/8/8/8/3QK3 w - -\ngo nodes 2000\n");
+        assert!(lines.iter().any(|l| l.contains(" multipv 2 ")));
+        assert!(lines.last().unwrap().starts_with("bestmove d1d5"));
+
+        let lines = run("position startpos moves e2e5\nfoo\ngo wtime 100\n");
+        assert_eq!(lines[0], "info string illegal move e2e5");
+        assert_eq!(lines[1], "info string unknown command foo");
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+
+        // there's a best move whatever the GUI sends with go
+        let lines = run("go searchmoves e2e3 b1c3 depth 1\ngo depth x nodes 100\ngo foo 1 depth 1\n");
+        let bestmoves = lines.iter().filter(|l| l.starts_with("bestmove ")).count();
+        assert_eq!(bestmoves, 3);
+        assert!(lines.iter().any(|l| l.starts_with("info string invalid depth x")));
+
+        // a network file is used from the next position on
+        let path = std::env::temp_dir().join("oxide-gambit-uci.nnue");
+        Network::zeroed(32, 8).save(&path).unwrap();
+        let mut uci = Uci::new(Output::default());
+        uci.command(&format!("setoption name EvalFile value {}", path.display()))
+            .unwrap();
+        uci.command("position startpos moves e2e3").unwrap();
+        assert!(uci.state.nnue.is_some());
+        uci.command("setoption name EvalFile value <empty>").unwrap();
+        assert!(uci.state.nnue.is_none());
+        std::fs::remove_file(path).unwrap();
+
+        let lines = run("bench 2\nbench x\n");
+        assert!(lines[0].starts_with("nodes "));
+        assert!(lines[1].starts_with("nps "));
+        assert!(lines[2].starts_with("info string invalid depth"));
+    }
+
+    #[test]
+    fn test_ponder() {
+        let output = Output::default();
+        let mut uci = Uci::new(output.clone());
+        uci.command("go ponder movetime 10").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        // no best move until the ponder hit
+        assert!(!output.lines().iter().any(|l| l.starts_with("bestmove")));
+        uci.command("ponderhit").unwrap();
+        uci.run("".as_bytes()).unwrap();
+        assert!(output.lines().last().unwrap().starts_with("bestmove"));
+
+        // stopping an infinite search still gives a move
+        uci.command("go infinite").unwrap();
+        uci.command("stop").unwrap();
+        assert!(output.lines().last().unwrap().starts_with("bestmove"));
+        assert!(!uci.command("quit").unwrap());
+    }
+}
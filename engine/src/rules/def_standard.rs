@@ -50,7 +50,7 @@ impl<const T: Team> PieceTrait for Pawn<T> {
             .ch("♟︎")
             .fen_ch('p')
             .name("Pawn")
-            .value(1)
+            .value(100)
             .sprite_index(0)
             .build(T)
     }
@@ -59,7 +59,7 @@ impl<const T: Team> PieceTrait for Pawn<T> {
         NormalMoves::new()
             .add_move(0, Self::DIR)
             .add_attack(1, Self::DIR)
-            .add_attack(1, Self::DIR)
+            .add_attack(-1, Self::DIR)
     }
 }
 
@@ -71,7 +71,7 @@ impl<const T: Team> PieceTrait for Rook<T> {
             .ch("♜")
             .fen_ch('r')
             .name("Rook")
-            .value(5)
+            .value(500)
             .attacks(&Direction::ORTHOGONAL)
             .sprite_index(1)
             .build(T)
@@ -86,10 +86,22 @@ impl<const T: Team> PieceTrait for Knight<T> {
             .ch("♞")
             .fen_ch('n')
             .name("Knight")
-            .value(3)
+            .value(320)
             .sprite_index(2)
             .build(T)
     }
+
+    fn moves(&self) -> NormalMoves {
+        NormalMoves::new()
+            .add(1, 2)
+            .add(2, 1)
+            .add(2, -1)
+            .add(1, -2)
+            .add(-1, -2)
+            .add(-2, -1)
+            .add(-2, 1)
+            .add(-1, 2)
+    }
 }
 
 pub struct Bishop<const T: Team>;
@@ -100,7 +112,7 @@ impl<const T: Team> PieceTrait for Bishop<T> {
             .ch("♝")
             .fen_ch('b')
             .name("Bishop")
-            .value(3)
+            .value(330)
             .attacks(&Direction::DIAGONAL)
             .sprite_index(3)
             .build(T)
@@ -115,7 +127,7 @@ impl<const T: Team> PieceTrait for Queen<T> {
             .ch("♛")
             .fen_ch('q')
             .name("Queen")
-            .value(9)
+            .value(900)
             .attacks(&Direction::ALL)
             .sprite_index(4)
             .build(T)
@@ -134,4 +146,16 @@ impl<const T: Team> PieceTrait for King<T> {
             .sprite_index(5)
             .build(T)
     }
+
+    fn moves(&self) -> NormalMoves {
+        NormalMoves::new()
+            .add(1, 0)
+            .add(1, 1)
+            .add(0, 1)
+            .add(-1, 1)
+            .add(-1, 0)
+            .add(-1, -1)
+            .add(0, -1)
+            .add(1, -1)
+    }
 }
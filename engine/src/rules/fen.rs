@@ -8,6 +8,9 @@ use anyhow::{bail, Context, Result};
 
 use super::Rules;
 
+/// The standard starting position
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
 impl State {
     /// loads a FEN string into the board state
     ///
@@ -125,6 +128,7 @@ impl State {
         let mut out = State::new(rules);
         out.board_state = board_state;
         out.turn = turn;
+        out.refresh();
         out.moves = Moves::generate(&out.board_state);
         Ok(out)
     }
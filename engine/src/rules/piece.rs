@@ -27,7 +27,42 @@ pub enum Piece {
     BlackKing,
 }
 
-pub trait PieceTrait {
+/// The kind of a piece, regardless of which team it is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+pub enum PieceKind {
+    Pawn,
+    Rook,
+    Knight,
+    Bishop,
+    Queen,
+    King,
+}
+
+impl PieceKind {
+    /// The piece of this kind on the given team
+    pub fn piece(self, team: Team) -> Piece {
+        use Piece::*;
+        use PieceKind::*;
+
+        match (team, self) {
+            (Team::White, Pawn) => WhitePawn,
+            (Team::White, Rook) => WhiteRook,
+            (Team::White, Knight) => WhiteKnight,
+            (Team::White, Bishop) => WhiteBishop,
+            (Team::White, Queen) => WhiteQueen,
+            (Team::White, King) => WhiteKing,
+            (Team::Black, Pawn) => BlackPawn,
+            (Team::Black, Rook) => BlackRook,
+            (Team::Black, Knight) => BlackKnight,
+            (Team::Black, Bishop) => BlackBishop,
+            (Team::Black, Queen) => BlackQueen,
+            (Team::Black, King) => BlackKing,
+        }
+    }
+}
+
+/// Pieces are shared between search threads, so they have to be `Send + Sync`
+pub trait PieceTrait: Send + Sync {
     /// Get information about the piece
     fn info(&self) -> PieceInfo;
     /// Return a list of moves
@@ -75,6 +110,36 @@ impl Piece {
         }
     }
 
+    /// The kind of this piece, if there is one
+    pub fn kind(self) -> Option<PieceKind> {
+        use Piece::*;
+
+        match self {
+            Empty | Captured => None,
+            WhitePawn | BlackPawn => Some(PieceKind::Pawn),
+            WhiteRook | BlackRook => Some(PieceKind::Rook),
+            WhiteKnight | BlackKnight => Some(PieceKind::Knight),
+            WhiteBishop | BlackBishop => Some(PieceKind::Bishop),
+            WhiteQueen | BlackQueen => Some(PieceKind::Queen),
+            WhiteKing | BlackKing => Some(PieceKind::King),
+        }
+    }
+
+    /// The team this piece is on, if there is one
+    pub fn team(self) -> Option<Team> {
+        use Piece::*;
+
+        match self {
+            Empty | Captured => None,
+            WhitePawn | WhiteRook | WhiteKnight | WhiteBishop | WhiteQueen | WhiteKing => {
+                Some(Team::White)
+            }
+            BlackPawn | BlackRook | BlackKnight | BlackBishop | BlackQueen | BlackKing => {
+                Some(Team::Black)
+            }
+        }
+    }
+
     pub fn info(self) -> Option<PieceInfo> {
         if let Some(piece) = self.piece() {
             Some(piece.info())
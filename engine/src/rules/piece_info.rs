@@ -16,8 +16,8 @@ pub struct PieceInfo {
     pub ch: &'static str,
     /// Character used to represent the piece in a FEN string
     pub fen_ch: Option<char>,
-    /// Value of the piece
-    pub value: u8,
+    /// Value of the piece, in centipawns
+    pub value: u16,
     /// Directions that the piece can attack in
     pub attacks: Vec<Direction>,
     /// Team that the piece belongs to
@@ -42,8 +42,8 @@ impl PieceInfo {
         pub fn ch(ch: &'static str);
         /// Set the character that represents the piece in a FEN string
         pub fn fen_ch(ch: char) => Some(ch);
-        /// Set the value of the piece
-        pub fn value(value: u8);
+        /// Set the value of the piece in centipawns
+        pub fn value(value: u16);
         /// Set the directions that the piece can attack in
         pub fn attacks(attacks: &[Direction]) => attacks.to_vec();
         /// Set the index of the piece in the sprite sheet
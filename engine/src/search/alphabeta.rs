@@ -0,0 +1,912 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    time::Instant,
+};
+
+use crate::{
+    chess::square::Square,
+    eval::{Evaluator, Score},
+    move_gen::moves::Move,
+    state::{zobrist::splitmix, State},
+};
+
+use super::{
+    limits::{Progress, SearchLimits},
+    options::SearchOptions,
+    quiescence::quiescence,
+    skill::SKILL_MULTI_PV,
+    tt::{Bound, TranspositionTable},
+    Eval, Searcher, MATE, MAX_PLY,
+};
+
+/// A move as the squares it goes from and to
+///
+/// Piece indices depend on how a position was reached, squares don't
+pub type SquareMove = (Square, Square);
+
+/// What a search found
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The score from the perspective of the side to move
+    pub score: Score,
+    /// The best line found, starting with the move to play
+    pub pv: Vec<SquareMove>,
+    /// The best lines found, best first, see [SearchOptions::multi_pv]
+    pub lines: Vec<Line>,
+    /// The depth of the last completed iteration
+    pub depth: i32,
+    /// How many positions were searched
+    pub nodes: u64,
+}
+
+/// One of the lines found by a multi-PV search
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Line {
+    pub score: Score,
+    pub pv: Vec<SquareMove>,
+}
+
+impl Line {
+    /// The score the way people want to see it
+    pub fn eval(&self) -> Eval {
+        Eval::new(self.score)
+    }
+}
+
+impl SearchResult {
+    /// The score the way people want to see it
+    pub fn eval(&self) -> Eval {
+        Eval::new(self.score)
+    }
+
+    /// The reply we expect to the best move, which is worth thinking about on the opponent's time
+    pub fn ponder_move(&self) -> Option<SquareMove> {
+        self.pv.get(1).copied()
+    }
+}
+
+/// Called with the progress of the search after every iteration
+pub type ProgressCallback = Box<dyn FnMut(&Progress) + Send>;
+
+/// An iterative deepening alpha-beta search
+///
+/// With more than one thread this is Lazy SMP: every thread searches the same position,
+/// sharing what they find through the transposition table
+pub struct AlphaBeta {
+    pub options: SearchOptions,
+    pub tt: Arc<TranspositionTable>,
+    /// The first worker is the main thread, the rest are helpers
+    workers: Vec<Worker>,
+    progress: Option<ProgressCallback>,
+    /// Counts the searches, so a weakened search doesn't always pick the same move
+    seed: u64,
+}
+
+impl Default for AlphaBeta {
+    fn default() -> Self {
+        Self::new(SearchOptions::default())
+    }
+}
+
+impl AlphaBeta {
+    pub fn new(options: SearchOptions) -> Self {
+        Self {
+            options,
+            tt: Arc::new(TranspositionTable::new(18)),
+            workers: Vec::new(),
+            progress: None,
+            seed: 0,
+        }
+    }
+
+    /// Forgets everything learnt from previous searches
+    pub fn clear(&mut self) {
+        self.tt.clear();
+        for worker in &mut self.workers {
+            worker.clear();
+        }
+    }
+
+    /// Calls `callback` after every iteration of every search from now on
+    pub fn on_progress(&mut self, callback: impl FnMut(&Progress) + Send + 'static) {
+        self.progress = Some(Box::new(callback));
+    }
+
+    /// Sends the progress of every search from now on to the returned channel,
+    /// for front ends running the search on another thread
+    pub fn progress_channel(&mut self) -> Receiver<Progress> {
+        let (sender, receiver) = mpsc::channel();
+        self.on_progress(move |progress| {
+            // nobody listening is fine
+            let _ = sender.send(progress.clone());
+        });
+        receiver
+    }
+
+    /// Searches the position one ply deeper at a time until a limit is reached
+    ///
+    /// The result always comes from the main thread, so with one thread and no time limit
+    /// it doesn't depend on timing at all
+    ///
+    /// Below full [Skill](super::skill::Skill) the limits are cut down to what the level allows
+    /// and the move is picked from a few of the best lines
+    pub fn search(&mut self, state: &mut State, limits: &SearchLimits) -> SearchResult {
+        let skill = self.options.skill;
+        let mut options = self.options.clone();
+        if !skill.full_strength() {
+            options.multi_pv = options.multi_pv.max(SKILL_MULTI_PV);
+        }
+        let limits = &skill.limit(limits);
+
+        let threads = options.threads.max(1);
+        while self.workers.len() < threads {
+            let id = self.workers.len();
+            self.workers.push(Worker::new(id, self.tt.clone()));
+        }
+        self.workers.truncate(threads);
+
+        let shared = Arc::new(Shared::new(threads, limits.clone()));
+        for worker in &mut self.workers {
+            worker.options = options.clone();
+            worker.shared = shared.clone();
+        }
+
+        let progress = &mut self.progress;
+        let (main, helpers) = self
+            .workers
+            .split_first_mut()
+            .expect("there is always a main thread");
+        let mut result = std::thread::scope(|scope| {
+            for helper in helpers {
+                let mut state = state.clone();
+                // helpers keep going until the main thread is done
+                scope.spawn(move || helper.iterate(&mut state, &mut |_| ()));
+            }
+
+            let result = match progress {
+                Some(progress) => main.iterate(state, progress),
+                None => main.iterate(state, &mut |_| ()),
+            };
+            // the best move mustn't be played before the opponent has moved
+            limits.wait();
+            shared.stop.store(true, Ordering::Relaxed);
+            result
+        });
+
+        result.nodes = shared.nodes();
+        if !skill.full_strength() {
+            self.seed += 1;
+            let pick = skill.pick(state, &result.lines, splitmix(self.seed) ^ state.hash);
+            if pick > 0 {
+                let line = result.lines.remove(pick);
+                result.score = line.score;
+                result.pv = line.pv.clone();
+                result.lines.insert(0, line);
+            }
+            result.lines.truncate(self.options.multi_pv.max(1));
+        }
+        result
+    }
+}
+
+impl Searcher for AlphaBeta {
+    fn search(&mut self, state: &mut State, limits: &SearchLimits) -> SearchResult {
+        AlphaBeta::search(self, state, limits)
+    }
+
+    fn clear(&mut self) {
+        AlphaBeta::clear(self);
+    }
+}
+
+/// A node counter on its own cache line, so threads don't slow each other down
+#[derive(Default)]
+#[repr(align(64))]
+struct Counter(AtomicU64);
+
+/// What the threads of one search share
+struct Shared {
+    /// Set when every thread should give up
+    stop: AtomicBool,
+    /// How many positions each thread has searched
+    nodes: Vec<Counter>,
+    limits: SearchLimits,
+    start: Instant,
+}
+
+impl Shared {
+    fn new(threads: usize, limits: SearchLimits) -> Self {
+        Self {
+            stop: AtomicBool::new(false),
+            nodes: (0..threads).map(|_| Counter::default()).collect(),
+            limits,
+            start: Instant::now(),
+        }
+    }
+
+    fn nodes(&self) -> u64 {
+        self.nodes.iter().map(|n| n.0.load(Ordering::Relaxed)).sum()
+    }
+}
+
+/// The state of the search on one thread
+struct Worker {
+    /// 0 for the main thread
+    id: usize,
+    options: SearchOptions,
+    tt: Arc<TranspositionTable>,
+    shared: Arc<Shared>,
+    evaluator: Evaluator,
+    /// Quiet moves that caused a beta cutoff, two per ply
+    killers: Vec<[Option<SquareMove>; 2]>,
+    /// How often quiet moves caused a beta cutoff, by from and to square
+    history: Vec<[i32; 64]>,
+    nodes: u64,
+    seldepth: i32,
+    /// Root moves already reported by a multi-PV search
+    root_excluded: Vec<SquareMove>,
+    /// The first iteration always finishes, so there is a move to play
+    can_stop: bool,
+    /// When the time limit started counting, which is later than the start of the search when pondering
+    clock: Instant,
+    /// Whether the search was still pondering at the last node, to start the clock on the ponder hit
+    pondering: bool,
+}
+
+/// Margins for the pruning near the leaves, by depth
+const REVERSE_FUTILITY_MARGIN: Score = 80;
+const FUTILITY_MARGIN: [Score; 4] = [0, 150, 250, 350];
+const RAZOR_MARGIN: [Score; 4] = [0, 300, 450, 600];
+const LATE_MOVE_COUNT: [usize; 4] = [0, 4, 7, 12];
+
+/// How wide the first aspiration window is on each side of the last score
+const ASPIRATION_WINDOW: Score = 25;
+/// The first depth to use aspiration windows at
+const ASPIRATION_DEPTH: i32 = 4;
+
+impl Worker {
+    fn new(id: usize, tt: Arc<TranspositionTable>) -> Self {
+        Self {
+            id,
+            options: SearchOptions::default(),
+            tt,
+            shared: Arc::new(Shared::new(id + 1, SearchLimits::default())),
+            evaluator: Evaluator::default(),
+            killers: vec![[None; 2]; MAX_PLY as usize],
+            history: vec![[0; 64]; 64],
+            nodes: 0,
+            seldepth: 0,
+            root_excluded: Vec::new(),
+            can_stop: false,
+            clock: Instant::now(),
+            pondering: false,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.evaluator.clear();
+        self.killers.fill([None; 2]);
+        self.history.fill([0; 64]);
+    }
+
+    fn stopped(&self) -> bool {
+        self.can_stop && self.shared.stop.load(Ordering::Relaxed)
+    }
+
+    /// Counts a node, and on the main thread checks whether it's time to stop
+    fn count_node(&mut self) {
+        self.nodes += 1;
+        self.shared.nodes[self.id]
+            .0
+            .store(self.nodes, Ordering::Relaxed);
+        if self.id != 0 || !self.can_stop {
+            return;
+        }
+
+        let limits = &self.shared.limits;
+        if limits.pondering() {
+            // the limits don't apply yet, but stopping still works
+            self.pondering = true;
+            if limits.stopped() {
+                self.shared.stop.store(true, Ordering::Relaxed);
+            }
+            return;
+        }
+        if self.pondering {
+            // the clock starts on the ponder hit
+            self.pondering = false;
+            self.clock = Instant::now();
+        }
+        let out_of_nodes = match limits.nodes {
+            Some(nodes) => self.shared.nodes() >= nodes,
+            None => false,
+        };
+        // looking at the clock is slow, so only do it every so often
+        let out_of_time = self.nodes & 1023 == 0
+            && match limits.movetime {
+                _ if limits.stopped() => true,
+                Some(movetime) => self.clock.elapsed() >= movetime,
+                None => false,
+            };
+        if out_of_nodes || out_of_time {
+            self.shared.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Iterative deepening until the depth limit, or until told to stop
+    ///
+    /// Only the main thread looks at the depth limit, helpers keep going until it's done
+    fn iterate(&mut self, state: &mut State, progress: &mut dyn FnMut(&Progress)) -> SearchResult {
+        self.nodes = 0;
+        self.clock = Instant::now();
+        self.pondering = false;
+        self.seldepth = 0;
+        // helpers can give up straight away, the main thread has to find a move first
+        self.can_stop = self.id != 0;
+        self.killers.fill([None; 2]);
+
+        // only the main thread looks for more than one line
+        let multi_pv = match self.id {
+            0 => self.options.multi_pv.max(1),
+            _ => 1,
+        };
+
+        let mut result = SearchResult::default();
+        'deepening: for depth in 1..MAX_PLY {
+            // half the helpers stay a ply ahead so the threads don't all search the same tree
+            let depth = (depth + (self.id % 2) as i32).min(MAX_PLY - 1);
+
+            // each line leaves out the first moves of the lines before it
+            let mut lines = Vec::<Line>::new();
+            self.root_excluded.clear();
+            while lines.len() < multi_pv {
+                let previous = result.lines.get(lines.len()).map_or(0, |line| line.score);
+                let mut pv = Vec::new();
+                let score = self.aspiration(state, depth, previous, &mut pv);
+                if self.stopped() {
+                    break 'deepening;
+                }
+                // no moves left to look at
+                let Some(&first) = pv.first() else { break };
+                self.root_excluded.push(first);
+                lines.push(Line { score, pv });
+            }
+            lines.sort_by_key(|line| std::cmp::Reverse(line.score));
+
+            result = SearchResult {
+                score: lines.first().map_or(0, |line| line.score),
+                pv: lines
+                    .first()
+                    .map(|line| line.pv.clone())
+                    .unwrap_or_default(),
+                lines,
+                depth,
+                nodes: self.nodes,
+            };
+
+            let time = self.shared.start.elapsed();
+            let nodes = self.shared.nodes();
+            for (i, line) in result.lines.iter().enumerate() {
+                progress(&Progress {
+                    depth,
+                    seldepth: self.seldepth,
+                    multi_pv: i + 1,
+                    score: line.score,
+                    nodes,
+                    nps: (nodes as u128 * 1_000_000 / time.as_micros().max(1)) as u64,
+                    hashfull: self.tt.hashfull(),
+                    time,
+                    pv: line.pv.clone(),
+                });
+            }
+
+            self.can_stop = true;
+            let limits = &self.shared.limits;
+            if self.stopped() || limits.stopped() {
+                break;
+            }
+            // a pondering search goes deeper until the ponder hit
+            let max_depth = limits.depth.unwrap_or(MAX_PLY - 1);
+            if self.id == 0 && depth >= max_depth && !limits.pondering() {
+                break;
+            }
+        }
+        self.root_excluded.clear();
+        result
+    }
+
+    /// Searches with a narrow window around the score from the last iteration,
+    /// widening it whenever the score falls outside
+    fn aspiration(
+        &mut self,
+        state: &mut State,
+        depth: i32,
+        previous: Score,
+        pv: &mut Vec<SquareMove>,
+    ) -> Score {
+        // the first few iterations are too unstable to guess from
+        if depth < ASPIRATION_DEPTH {
+            return self.negamax(state, depth, 0, -MATE, MATE, pv, None);
+        }
+
+        let mut delta = ASPIRATION_WINDOW;
+        let mut alpha = (previous - delta).max(-MATE);
+        let mut beta = (previous + delta).min(MATE);
+        loop {
+            let score = self.negamax(state, depth, 0, alpha, beta, pv, None);
+            delta *= 2;
+            if score <= alpha && alpha > -MATE {
+                alpha = (score - delta).max(-MATE);
+            } else if score >= beta && beta < MATE {
+                beta = (score + delta).min(MATE);
+            } else {
+                return score;
+            }
+        }
+    }
+
+    /// The moves the side to move can make, best looking first
+    ///
+    /// These are pseudo-legal, they might leave the king in check
+    fn ordered_moves(
+        &self,
+        state: &State,
+        ply: i32,
+        tt_move: Option<SquareMove>,
+    ) -> Vec<(SquareMove, bool)> {
+        let board = &state.board_state;
+        let killers = self.killers[ply as usize];
+
+        let mut moves = state
+            .moves
+            .iter()
+            .filter(|m| board.get_info(m.piece).map(|info| info.team) == Some(state.turn))
+            .map(|m| {
+                let from = board.square_of(m.piece);
+                let capture = board.get_info(m.to).is_some();
+                let order = if Some((from, m.to)) == tt_move {
+                    i32::MAX
+                } else if capture {
+                    let victim = board.get_info(m.to).map_or(0, |info| info.value as i32);
+                    let attacker = board.get_info(m.piece).map_or(0, |info| info.value as i32);
+                    1 << 24 | victim << 8 | (1000 - attacker) >> 2
+                } else if killers.contains(&Some((from, m.to))) {
+                    1 << 23
+                } else {
+                    self.history[*from as usize][*m.to as usize].min((1 << 23) - 1)
+                };
+                ((from, m.to), capture, order)
+            })
+            .collect::<Vec<_>>();
+        // ties are broken by the squares so the order never depends on the hash set
+        moves.sort_by_key(|&(m, _, order)| (std::cmp::Reverse(order), m));
+        moves
+            .into_iter()
+            .map(|(m, capture, _)| (m, capture))
+            .collect()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &mut self,
+        state: &mut State,
+        mut depth: i32,
+        ply: i32,
+        mut alpha: Score,
+        mut beta: Score,
+        pv: &mut Vec<SquareMove>,
+        excluded: Option<SquareMove>,
+    ) -> Score {
+        pv.clear();
+        if self.stopped() {
+            return 0;
+        }
+        self.count_node();
+        self.seldepth = self.seldepth.max(ply);
+        let is_pv = beta - alpha > 1;
+
+        if ply > 0 {
+            if state.is_repetition() {
+                return 0;
+            }
+            // mate distance pruning, even mating right away can't beat a mate we already found
+            alpha = alpha.max(-MATE + ply);
+            beta = beta.min(MATE - ply - 1);
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+        if ply >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(state);
+        }
+
+        let in_check = state.in_check(state.turn);
+        if in_check && self.options.check_extension {
+            depth += 1;
+        }
+        if depth <= 0 {
+            return self.quiescence(state, alpha, beta);
+        }
+
+        let entry = match excluded {
+            Some(_) => None,
+            None => self.tt.probe(state.hash, ply),
+        };
+        if let Some(entry) = entry
+            && !is_pv
+            && entry.depth >= depth
+        {
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+            if cutoff {
+                return entry.score;
+            }
+        }
+        let tt_move = entry.and_then(|entry| entry.best);
+
+        let eval = self.evaluator.evaluate(state);
+        let quiet_node = !is_pv && !in_check && excluded.is_none();
+        let near_leaf = (depth as usize) < FUTILITY_MARGIN.len();
+
+        // so far ahead that nothing is going to bring it back under beta
+        if self.options.reverse_futility
+            && quiet_node
+            && depth <= 6
+            && eval - REVERSE_FUTILITY_MARGIN * depth >= beta
+            && eval.abs() < MATE - MAX_PLY
+        {
+            return eval;
+        }
+
+        // so far behind that only captures could help
+        if self.options.razoring
+            && quiet_node
+            && near_leaf
+            && eval + RAZOR_MARGIN[depth as usize] < alpha
+        {
+            let score = self.quiescence(state, alpha, beta);
+            if score < alpha {
+                return score;
+            }
+        }
+
+        let futile = self.options.futility
+            && quiet_node
+            && near_leaf
+            && eval + FUTILITY_MARGIN[depth as usize] <= alpha;
+
+        // the tt move is singular if every other move is clearly worse,
+        // then it's worth looking at more closely
+        let mut singular = false;
+        if self.options.singular_extension
+            && excluded.is_none()
+            && ply > 0
+            && depth >= 6
+            && let Some(entry) = entry
+            && let Some(tt_move) = tt_move
+            && entry.bound != Bound::Upper
+            && entry.depth >= depth - 3
+            && entry.score.abs() < MATE - MAX_PLY
+        {
+            let singular_beta = entry.score - 2 * depth;
+            let mut line = Vec::new();
+            let score = self.negamax(
+                state,
+                depth / 2,
+                ply,
+                singular_beta - 1,
+                singular_beta,
+                &mut line,
+                Some(tt_move),
+            );
+            singular = score < singular_beta;
+        }
+
+        let mut best = -MATE;
+        let mut best_move = None;
+        let mut legal = 0;
+        let mut quiets = 0;
+        let mut line = Vec::new();
+        let original_alpha = alpha;
+
+        for ((from, to), capture) in self.ordered_moves(state, ply, tt_move) {
+            if Some((from, to)) == excluded
+                || (ply == 0 && self.root_excluded.contains(&(from, to)))
+            {
+                continue;
+            }
+            // only skip moves once we know we aren't getting mated
+            let can_prune = legal > 0 && !capture && best > -MATE + MAX_PLY;
+            if can_prune && futile {
+                continue;
+            }
+            if can_prune
+                && self.options.late_move_pruning
+                && quiet_node
+                && near_leaf
+                && quiets >= LATE_MOVE_COUNT[depth as usize]
+            {
+                continue;
+            }
+
+            let team = state.turn;
+            state.make_move(from, to);
+            if state.in_check(team) {
+                state.unmake_move();
+                continue;
+            }
+            legal += 1;
+            if !capture {
+                quiets += 1;
+            }
+
+            let extension = (singular && Some((from, to)) == tt_move) as i32;
+            let new_depth = depth - 1 + extension;
+            // principal variation search, prove the first move is best with null windows
+            let mut score;
+            if legal == 1 {
+                score = -self.negamax(state, new_depth, ply + 1, -beta, -alpha, &mut line, None);
+            } else {
+                score = -self.negamax(
+                    state,
+                    new_depth,
+                    ply + 1,
+                    -alpha - 1,
+                    -alpha,
+                    &mut line,
+                    None,
+                );
+                if score > alpha && score < beta {
+                    score =
+                        -self.negamax(state, new_depth, ply + 1, -beta, -alpha, &mut line, None);
+                }
+            }
+            state.unmake_move();
+
+            if score > best {
+                best = score;
+                best_move = Some((from, to));
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push((from, to));
+                pv.extend_from_slice(&line);
+            }
+            if alpha >= beta {
+                if !capture {
+                    self.add_killer(ply, (from, to));
+                    self.history[*from as usize][*to as usize] += depth * depth;
+                }
+                break;
+            }
+        }
+
+        if self.stopped() {
+            return 0;
+        }
+        if legal == 0 {
+            return match excluded {
+                // the only move was the excluded one, so it's certainly singular
+                Some(_) => alpha,
+                None if in_check => -MATE + ply,
+                None => 0,
+            };
+        }
+
+        // the root score doesn't count when some of its moves were left out
+        if excluded.is_none() && (ply > 0 || self.root_excluded.is_empty()) {
+            let bound = if best >= beta {
+                Bound::Lower
+            } else if alpha > original_alpha {
+                Bound::Exact
+            } else {
+                Bound::Upper
+            };
+            self.tt
+                .store(state.hash, ply, depth, best, bound, best_move);
+        }
+        best
+    }
+
+    /// Only called from a node that's already been counted
+    fn quiescence(&mut self, state: &mut State, alpha: Score, beta: Score) -> Score {
+        let mut pv: Vec<Move> = Vec::new();
+        quiescence(state, &mut self.evaluator, alpha, beta, &mut pv)
+    }
+
+    fn add_killer(&mut self, ply: i32, m: SquareMove) {
+        let killers = &mut self.killers[ply as usize];
+        if killers[0] != Some(m) {
+            killers[1] = killers[0];
+            killers[0] = Some(m);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rules;
+    use std::time::Duration;
+
+    fn state(fen: &str) -> State {
+        State::from_FEN(fen, Rules::standard()).unwrap()
+    }
+
+    #[test]
+    fn test_mate_in_one() {
+        // Ra8#
+        let mut state = state("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let result = AlphaBeta::default().search(&mut state, &SearchLimits::depth(3));
+
+        assert_eq!(result.pv[0], (Square(0), Square(56)));
+        assert_eq!(result.score, MATE - 1);
+        assert_eq!(result.eval(), Eval::Mate(1));
+        assert!(state.history.is_empty());
+    }
+
+    #[test]
+    fn test_options() {
+        // Nc7+ forks the king and the queen
+        let fen = "q3k3/8/8/3N4/8/8/8/4K3 w - - 0 1";
+        let all = AlphaBeta::default().search(&mut state(fen), &SearchLimits::depth(4));
+        assert_eq!(all.pv[0], (Square(35), Square(50)));
+
+        let options = [
+            SearchOptions {
+                check_extension: false,
+                ..Default::default()
+            },
+            SearchOptions {
+                singular_extension: false,
+                ..Default::default()
+            },
+            SearchOptions {
+                reverse_futility: false,
+                ..Default::default()
+            },
+            SearchOptions {
+                futility: false,
+                ..Default::default()
+            },
+            SearchOptions {
+                razoring: false,
+                ..Default::default()
+            },
+            SearchOptions {
+                late_move_pruning: false,
+                ..Default::default()
+            },
+            SearchOptions::none(),
+        ];
+        for options in options {
+            let result =
+                AlphaBeta::new(options.clone()).search(&mut state(fen), &SearchLimits::depth(4));
+            assert_eq!(result.pv[0], all.pv[0], "{options:?}");
+        }
+    }
+
+    #[test]
+    fn test_threads() {
+        let fen = "q3k3/8/8/3N4/8/8/8/4K3 w - - 0 1";
+        // one thread always searches exactly the same tree
+        let first = AlphaBeta::default().search(&mut state(fen), &SearchLimits::depth(5));
+        let second = AlphaBeta::default().search(&mut state(fen), &SearchLimits::depth(5));
+        assert_eq!(first, second);
+
+        let mut smp = AlphaBeta::new(SearchOptions {
+            threads: 4,
+            ..Default::default()
+        });
+        let mut position = state(fen);
+        let result = smp.search(&mut position, &SearchLimits::depth(5));
+        assert_eq!(result.pv[0], first.pv[0]);
+        assert!(position.history.is_empty());
+    }
+
+    #[test]
+    fn test_limits() {
+        let fen = "r1bqk2r/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP3PPP/R1BQKB1R b KQkq - 0 7";
+        let result = AlphaBeta::default().search(&mut state(fen), &SearchLimits::nodes(500));
+        assert!(result.nodes <= 500);
+        assert!(!result.pv.is_empty());
+
+        // an infinite search only stops when it's told to
+        let mut search = AlphaBeta::default();
+        let progress = search.progress_channel();
+        let limits = SearchLimits::infinite();
+        let handle = {
+            let limits = limits.clone();
+            std::thread::spawn(move || search.search(&mut state(fen), &limits))
+        };
+
+        let first = progress.recv().unwrap();
+        assert_eq!(first.depth, 1);
+        limits.stop();
+        let result = handle.join().unwrap();
+        assert!(result.depth >= 1);
+        assert!(progress.try_iter().all(|p| p.depth > 1));
+    }
+
+    #[test]
+    fn test_ponder() {
+        let fen = "r1bqk2r/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP3PPP/R1BQKB1R b KQkq - 0 7";
+        let result = AlphaBeta::default().search(&mut state(fen), &SearchLimits::depth(3));
+        assert_eq!(result.ponder_move(), result.pv.get(1).copied());
+
+        // a pondering search ignores its limits until the ponder hit
+        let movetime = Duration::from_millis(50);
+        let limits = SearchLimits::movetime(movetime).ponder();
+        let handle = {
+            let limits = limits.clone();
+            std::thread::spawn(move || AlphaBeta::default().search(&mut state(fen), &limits))
+        };
+        std::thread::sleep(4 * movetime);
+        assert!(!handle.is_finished());
+
+        let hit = Instant::now();
+        limits.ponder_hit();
+        let result = handle.join().unwrap();
+        assert!(hit.elapsed() >= movetime);
+        assert!(result.depth > 1);
+
+        // pondering goes past a depth limit, which stops the search once it's a normal one
+        let limits = SearchLimits::depth(1).ponder();
+        let handle = {
+            let limits = limits.clone();
+            std::thread::spawn(move || AlphaBeta::default().search(&mut state(fen), &limits))
+        };
+        std::thread::sleep(movetime);
+        assert!(!handle.is_finished());
+        limits.ponder_hit();
+        assert!(handle.join().unwrap().depth > 1);
+
+        // after a ponder miss the search stops straight away
+        let limits = SearchLimits::infinite().ponder();
+        let handle = {
+            let limits = limits.clone();
+            std::thread::spawn(move || AlphaBeta::default().search(&mut state(fen), &limits))
+        };
+        std::thread::sleep(movetime);
+        limits.stop();
+        assert!(!handle.join().unwrap().pv.is_empty());
+    }
+
+    #[test]
+    fn test_multi_pv() {
+        let fen = "q3k3/8/8/3N4/8/8/8/4K3 w - - 0 1";
+        let single = AlphaBeta::default().search(&mut state(fen), &SearchLimits::depth(4));
+
+        let mut search = AlphaBeta::new(SearchOptions {
+            multi_pv: 3,
+            ..Default::default()
+        });
+        let result = search.search(&mut state(fen), &SearchLimits::depth(4));
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].pv[0], single.pv[0]);
+        assert_eq!(result.lines[0].score, result.score);
+        for pair in result.lines.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+            assert_ne!(pair[0].pv[0], pair[1].pv[0]);
+        }
+
+        // asking for more lines than there are moves gives every move
+        let mut search = AlphaBeta::new(SearchOptions {
+            multi_pv: 100,
+            ..Default::default()
+        });
+        let result = search.search(
+            &mut state("k7/8/8/8/8/8/8/7K w - - 0 1"),
+            &SearchLimits::depth(2),
+        );
+        assert_eq!(result.lines.len(), 3);
+    }
+}
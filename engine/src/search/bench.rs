@@ -0,0 +1,94 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use crate::{
+    rules::{fen::START_FEN, Rules},
+    state::State,
+};
+
+use super::{alphabeta::AlphaBeta, limits::SearchLimits, options::SearchOptions};
+
+/// The depth [bench] searches to unless told otherwise
+pub const BENCH_DEPTH: i32 = 6;
+
+/// The positions [bench] searches, openings, middlegames and endgames
+pub const BENCH_POSITIONS: [&str; 8] = [
+    START_FEN,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 10",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "2kr3r/ppp2ppp/2n5/2b1p3/4P1b1/2NP1N2/PPP2PPP/R1B1KB1R b KQ - 0 9",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+    "8/8/4k3/3p4/3P4/4K3/8/8 w - - 0 1",
+];
+
+/// How long [bench] took and how much it searched
+#[derive(Clone, Debug, Default)]
+pub struct BenchResult {
+    /// The nodes searched in each position
+    pub nodes: Vec<u64>,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    /// The total number of nodes, the same on every machine for the same code
+    pub fn total_nodes(&self) -> u64 {
+        self.nodes.iter().sum()
+    }
+
+    /// Nodes per second
+    pub fn nps(&self) -> u64 {
+        (self.total_nodes() as f64 / self.elapsed.as_secs_f64().max(1e-9)) as u64
+    }
+}
+
+impl Display for BenchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "nodes {}", self.total_nodes())?;
+        write!(f, "nps {}", self.nps())
+    }
+}
+
+/// Searches every one of [BENCH_POSITIONS] to `depth` on one thread, from an empty
+/// transposition table each time
+///
+/// Nothing depends on the time or on other threads, so the node count only changes when
+/// the search or the evaluation does, which makes it a quick check that a change that
+/// shouldn't change how the engine plays really doesn't
+pub fn bench(depth: i32) -> Result<BenchResult> {
+    let mut search = AlphaBeta::new(SearchOptions {
+        threads: 1,
+        ..Default::default()
+    });
+    let limits = SearchLimits::depth(depth);
+
+    let mut result = BenchResult::default();
+    let start = Instant::now();
+    for fen in BENCH_POSITIONS {
+        let mut state = State::from_FEN(fen, Rules::standard())?;
+        search.clear();
+        result.nodes.push(search.search(&mut state, &limits).nodes);
+    }
+    result.elapsed = start.elapsed();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bench() {
+        let first = bench(2).unwrap();
+        let second = bench(2).unwrap();
+        assert_eq!(first.nodes.len(), BENCH_POSITIONS.len());
+        assert!(first.nodes.iter().all(|&nodes| nodes > 0));
+        assert_eq!(first.nodes, second.nodes);
+        assert!(bench(3).unwrap().total_nodes() > first.total_nodes());
+    }
+}
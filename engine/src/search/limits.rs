@@ -0,0 +1,204 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::eval::Score;
+
+use super::{alphabeta::SquareMove, Eval};
+
+/// When a search should stop
+///
+/// With no limits at all the search goes as deep as it can
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    /// Don't start an iteration deeper than this
+    pub depth: Option<i32>,
+    /// Stop after searching this many positions
+    pub nodes: Option<u64>,
+    /// Stop after this much time
+    pub movetime: Option<Duration>,
+    /// Don't return until told to stop, even when there is nothing left to search
+    pub infinite: bool,
+    /// Shared by every clone, so the search can be controlled from another thread
+    signals: Arc<Signals>,
+}
+
+/// What other threads tell a running search, which it can wait on instead of polling
+#[derive(Debug, Default)]
+struct Signals {
+    stop: AtomicBool,
+    /// While this is set the search is thinking on the opponent's time and ignores the
+    /// other limits, see [SearchLimits::ponder]
+    ponder: AtomicBool,
+    lock: Mutex<()>,
+    changed: Condvar,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: i32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    pub fn nodes(nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+
+    pub fn movetime(movetime: Duration) -> Self {
+        Self {
+            movetime: Some(movetime),
+            ..Default::default()
+        }
+    }
+
+    pub fn infinite() -> Self {
+        Self {
+            infinite: true,
+            ..Default::default()
+        }
+    }
+
+    /// Searches the position after the move we expect the opponent to play, see
+    /// [SearchResult::ponder_move](super::alphabeta::SearchResult::ponder_move)
+    ///
+    /// The search keeps going until either [SearchLimits::ponder_hit] is called, after which
+    /// the other limits apply as if the search had only just started, or it's stopped because
+    /// the opponent played something else and the result should be thrown away
+    ///
+    /// The time limit counts from the ponder hit, but a depth limit still applies to the
+    /// depth reached, so if pondering has already gone past it the search stops at the end
+    /// of the iteration it's in
+    pub fn ponder(self) -> Self {
+        self.signals.ponder.store(true, Ordering::Relaxed);
+        self
+    }
+
+    /// The opponent played the expected move, so carry on as a normal search
+    pub fn ponder_hit(&self) {
+        self.signals.ponder.store(false, Ordering::Relaxed);
+        self.notify();
+    }
+
+    /// Returns true until [SearchLimits::ponder_hit] is called on a pondering search
+    pub fn pondering(&self) -> bool {
+        self.signals.ponder.load(Ordering::Relaxed)
+    }
+
+    /// Stops the search from any thread, the best move found so far is returned
+    pub fn stop(&self) {
+        self.signals.stop.store(true, Ordering::Relaxed);
+        self.notify();
+    }
+
+    /// Returns true once someone has asked the search to stop
+    pub fn stopped(&self) -> bool {
+        self.signals.stop.load(Ordering::Relaxed)
+    }
+
+    /// Blocks until a search that has finished may return its move, which for an infinite
+    /// search is when it's stopped and for a pondering one on the ponder hit
+    pub fn wait(&self) {
+        let mut lock = self.signals.lock.lock().expect("nothing panics holding it");
+        while (self.infinite || self.pondering()) && !self.stopped() {
+            lock = self
+                .signals
+                .changed
+                .wait(lock)
+                .expect("nothing panics holding it");
+        }
+    }
+
+    /// Wakes up anything in [SearchLimits::wait]
+    fn notify(&self) {
+        let _lock = self.signals.lock.lock().expect("nothing panics holding it");
+        self.signals.changed.notify_all();
+    }
+}
+
+/// Time kept back for talking to the GUI, so the clock doesn't run out between moves
+pub const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// How long to think about a move with `time` left on the clock, gaining `increment` every move
+///
+/// The time is shared out evenly between the moves until the next time control,
+/// assuming there are 30 more if there isn't one
+pub fn time_for_move(time: Duration, increment: Duration, moves_to_go: Option<u32>) -> Duration {
+    let moves = moves_to_go.unwrap_or(30).max(1);
+    let share = time / moves + increment * 3 / 4;
+    share
+        .min(time.saturating_sub(MOVE_OVERHEAD))
+        .max(Duration::from_millis(1))
+}
+
+/// How the search is going, reported after every iteration
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub depth: i32,
+    /// The deepest ply reached
+    pub seldepth: i32,
+    /// Which of the lines this is, starting at 1, see [SearchOptions::multi_pv](super::options::SearchOptions::multi_pv)
+    pub multi_pv: usize,
+    /// The score from the perspective of the side to move
+    pub score: Score,
+    /// Positions searched by every thread
+    pub nodes: u64,
+    /// Positions searched per second
+    pub nps: u64,
+    /// How full the transposition table is, in permille
+    pub hashfull: usize,
+    pub time: Duration,
+    pub pv: Vec<SquareMove>,
+}
+
+impl Progress {
+    /// The score the way people want to see it
+    pub fn eval(&self) -> Eval {
+        Eval::new(self.score)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_time_for_move() {
+        let ms = Duration::from_millis;
+        assert_eq!(time_for_move(ms(30_000), ms(0), None), ms(1000));
+        assert_eq!(time_for_move(ms(10_000), ms(1000), Some(10)), ms(1750));
+        // never more than is left on the clock
+        assert_eq!(time_for_move(ms(100), ms(1000), Some(1)), ms(50));
+        assert_eq!(time_for_move(ms(0), ms(0), None), ms(1));
+    }
+
+    /// Checks that waiting on the limits blocks until `wake` is called
+    fn waits_for(limits: SearchLimits, wake: impl Fn(&SearchLimits)) {
+        let waiting = {
+            let limits = limits.clone();
+            std::thread::spawn(move || limits.wait())
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!waiting.is_finished());
+        wake(&limits);
+        waiting.join().unwrap();
+    }
+
+    #[test]
+    fn test_wait() {
+        // nothing to wait for
+        SearchLimits::depth(1).wait();
+        SearchLimits::movetime(Duration::from_secs(60)).wait();
+
+        waits_for(SearchLimits::infinite(), SearchLimits::stop);
+        waits_for(SearchLimits::depth(1).ponder(), SearchLimits::ponder_hit);
+    }
+}
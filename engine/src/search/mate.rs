@@ -0,0 +1,218 @@
+use rustc_hash::FxHashMap;
+
+use crate::state::State;
+
+use super::alphabeta::SquareMove;
+
+/// With this many moves or fewer left the attacker only tries checks,
+/// the last move of a mate is always a check so nothing is missed
+pub const CHECKS_ONLY: i32 = 1;
+
+/// Looks for a forced mate by the side to move in at most `n` moves
+///
+/// Returns the shortest mating line, with the defence that holds out longest,
+/// or `None` if there is no mate in `n` whatever the defender does
+pub fn find_mate(state: &mut State, n: i32) -> Option<Vec<SquareMove>> {
+    find_mate_with(state, n, CHECKS_ONLY)
+}
+
+/// Like [find_mate], with the attacker only trying checks once it has `checks_only`
+/// moves or fewer left
+///
+/// More than [CHECKS_ONLY] is much faster but misses mates with a quiet move near the
+/// end, so `None` no longer proves there isn't a mate
+pub fn find_mate_with(state: &mut State, n: i32, checks_only: i32) -> Option<Vec<SquareMove>> {
+    let mut solver = Solver {
+        checks_only,
+        ..Default::default()
+    };
+    // shallow misses are cheap and make the deeper searches faster
+    (1..=n).find_map(|moves| solver.attack(state, moves))
+}
+
+#[derive(Default)]
+struct Solver {
+    /// See [find_mate_with]
+    checks_only: i32,
+    /// The most moves each position with the attacker to move is known not to be mate in
+    refuted: FxHashMap<u64, i32>,
+    /// The reply that last saved the defender, by ply
+    refutations: Vec<Option<SquareMove>>,
+}
+
+/// A legal move for the side to move
+pub(super) struct Candidate {
+    pub m: SquareMove,
+    pub capture: bool,
+    pub check: bool,
+    /// The hash of the position after the move
+    pub hash: u64,
+}
+
+impl Solver {
+    /// The shortest mate in at most `n` moves, if there is one
+    fn attack(&mut self, state: &mut State, n: i32) -> Option<Vec<SquareMove>> {
+        if n <= 0 {
+            return None;
+        }
+        if let Some(&refuted) = self.refuted.get(&state.hash) && refuted >= n {
+            return None;
+        }
+
+        let mut candidates = legal_moves(state);
+        // checks are the most forcing, then captures
+        candidates.sort_by_key(|c| (!c.check, !c.capture, c.m));
+
+        let mut best: Option<Vec<SquareMove>> = None;
+        let mut limit = n;
+        for candidate in candidates {
+            if limit <= self.checks_only && !candidate.check {
+                continue;
+            }
+            state.make_move(candidate.m.0, candidate.m.1);
+            let defence = self.defend(state, limit);
+            state.unmake_move();
+
+            let Some(defence) = defence else { continue };
+            let mut line = vec![candidate.m];
+            line.extend(defence);
+            // any other mate has to be quicker than this one
+            limit = (line.len() as i32 + 1) / 2 - 1;
+            best = Some(line);
+            if limit == 0 {
+                break;
+            }
+        }
+
+        if best.is_none() {
+            self.refuted.insert(state.hash, n);
+        }
+        best
+    }
+
+    /// The longest the defender can hold out after the attacker's move, if every reply
+    /// is mated within the `n` moves the attacker had, counting the one just played
+    fn defend(&mut self, state: &mut State, n: i32) -> Option<Vec<SquareMove>> {
+        let mut replies = legal_moves(state);
+        if replies.is_empty() {
+            // checkmate, or a stalemate that throws the win away
+            return state.in_check(state.turn).then(Vec::new);
+        }
+        if n <= 1 {
+            return None;
+        }
+
+        let ply = state.history.len();
+        if self.refutations.len() <= ply {
+            self.refutations.resize(ply + 1, None);
+        }
+        // the reply that worked before is likely to work again, then captures
+        let refutation = self.refutations[ply];
+        replies.sort_by_key(|c| (Some(c.m) != refutation, !c.capture, c.m));
+
+        let mut longest = Vec::new();
+        for reply in replies {
+            state.make_move(reply.m.0, reply.m.1);
+            let mate = self.attack(state, n - 1);
+            state.unmake_move();
+
+            let Some(mate) = mate else {
+                self.refutations[ply] = Some(reply.m);
+                return None;
+            };
+            if mate.len() + 1 > longest.len() {
+                longest = vec![reply.m];
+                longest.extend(mate);
+            }
+        }
+        Some(longest)
+    }
+}
+
+/// The moves the side to move can make without leaving its king in check
+pub(super) fn legal_moves(state: &mut State) -> Vec<Candidate> {
+    let board = &state.board_state;
+    let team = state.turn;
+    let moves = state
+        .moves
+        .iter()
+        .filter(|m| board.get_info(m.piece).map(|info| info.team) == Some(team))
+        .map(|m| {
+            let capture = board.get_info(m.to).is_some();
+            ((board.square_of(m.piece), m.to), capture)
+        })
+        .collect::<Vec<_>>();
+
+    let mut legal = Vec::new();
+    for (m, capture) in moves {
+        state.make_move(m.0, m.1);
+        if !state.in_check(team) {
+            let check = state.in_check(state.turn);
+            let hash = state.hash;
+            legal.push(Candidate {
+                m,
+                capture,
+                check,
+                hash,
+            });
+        }
+        state.unmake_move();
+    }
+    legal
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rules;
+
+    /// Known problems and how many moves they are mate in
+    const PROBLEMS: [(&str, i32); 4] = [
+        // the first move is quiet
+        ("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1", 2),
+        ("r1b2k1r/ppppq3/5N1p/4P2Q/4PP2/1B6/PP5P/n2K2R1 w - - 1 0", 2),
+        // black mates, with the white king dragged up the board
+        (
+            "r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1",
+            3,
+        ),
+        // Philidor's legacy, ending in a smothered mate
+        ("r6k/6pp/8/6N1/8/1Q6/8/6K1 w - - 0 1", 4),
+    ];
+
+    /// Plays out a line and checks that it ends in mate
+    fn is_mate(fen: &str, line: &[SquareMove]) -> bool {
+        let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+        for &(from, to) in line {
+            state.make_move(from, to);
+        }
+        legal_moves(&mut state).is_empty() && state.in_check(state.turn)
+    }
+
+    #[test]
+    fn test_find_mate() {
+        for (fen, n) in PROBLEMS {
+            let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+            assert_eq!(find_mate(&mut state, n - 1), None, "{fen}");
+            let line = find_mate(&mut state, n).unwrap_or_else(|| panic!("no mate in {n}: {fen}"));
+            assert_eq!(line.len() as i32, 2 * n - 1, "{fen}");
+            assert!(is_mate(fen, &line), "{fen}");
+            assert!(state.history.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_checks_only() {
+        // only trying checks for the last two moves still finds mates ending in checks
+        for (fen, n) in &PROBLEMS[1..] {
+            let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+            let line = find_mate_with(&mut state, *n, 2).unwrap_or_else(|| panic!("{fen}"));
+            assert!(is_mate(fen, &line), "{fen}");
+        }
+
+        // but not one starting with a quiet move
+        let (fen, n) = PROBLEMS[0];
+        let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+        assert_eq!(find_mate_with(&mut state, n, 2), None);
+    }
+}
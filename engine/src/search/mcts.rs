@@ -0,0 +1,411 @@
+use std::time::Instant;
+
+use crate::{
+    eval::{Evaluator, Score},
+    state::{zobrist::splitmix, State},
+};
+
+use super::{
+    alphabeta::{Line, SearchResult, SquareMove},
+    limits::SearchLimits,
+    mate::legal_moves,
+    quiescence::quiescence,
+    Searcher, MATE, MAX_PLY,
+};
+
+/// How many playouts to run when the limits don't say when to stop
+pub const DEFAULT_PLAYOUTS: u64 = 10_000;
+
+/// The centipawn score that is worth a value of about 0.76, values are `tanh(score / VALUE_SCALE)`
+const VALUE_SCALE: f32 = 400.0;
+/// How much the priors favour moves that look good, lower is greedier
+const PRIOR_TEMPERATURE: f32 = 200.0;
+
+/// How the tree search is done
+#[derive(Clone, Debug, PartialEq)]
+pub struct MctsOptions {
+    /// How much unexplored moves are preferred to ones that have done well so far
+    pub exploration: f32,
+    /// How many random moves to play from a new leaf before evaluating it,
+    /// 0 evaluates the leaf itself
+    pub rollout_depth: usize,
+}
+
+impl Default for MctsOptions {
+    fn default() -> Self {
+        Self {
+            exploration: 1.5,
+            rollout_depth: 0,
+        }
+    }
+}
+
+/// A node of the search tree, for the position after its move
+struct Node {
+    /// `None` at the root
+    m: Option<SquareMove>,
+    /// How likely the move is to be best before it's been looked at
+    prior: f32,
+    visits: u32,
+    /// The sum of the values of the playouts through here, for the side that played the move
+    value: f32,
+    /// The first child and how many there are, set once the node has been expanded
+    children: Option<(usize, usize)>,
+    /// The value for the side to move if the game is over here
+    terminal: Option<f32>,
+}
+
+impl Node {
+    fn new(m: Option<SquareMove>, prior: f32) -> Self {
+        Self {
+            m,
+            prior,
+            visits: 0,
+            value: 0.0,
+            children: None,
+            terminal: None,
+        }
+    }
+
+    /// The average value of the playouts through here, for the side that played the move
+    fn q(&self) -> f32 {
+        match self.visits {
+            0 => 0.0,
+            visits => self.value / visits as f32,
+        }
+    }
+}
+
+/// A Monte Carlo tree search, choosing what to look at with PUCT
+///
+/// The priors come from evaluating the position after every move, and the value of a new leaf
+/// is its quiescence score, optionally after a random rollout
+pub struct Mcts {
+    pub options: MctsOptions,
+    evaluator: Evaluator,
+    /// The root is the first node, children are stored next to each other
+    nodes: Vec<Node>,
+    /// Where the random rollouts are in the splitmix sequence
+    seed: u64,
+}
+
+impl Default for Mcts {
+    fn default() -> Self {
+        Self::new(MctsOptions::default())
+    }
+}
+
+impl Mcts {
+    pub fn new(options: MctsOptions) -> Self {
+        Self {
+            options,
+            evaluator: Evaluator::default(),
+            nodes: Vec::new(),
+            seed: 0,
+        }
+    }
+
+    /// Forgets everything learnt from previous searches
+    pub fn clear(&mut self) {
+        self.evaluator.clear();
+        self.seed = 0;
+    }
+
+    /// Runs playouts until a limit is reached
+    ///
+    /// There are no iterations, so the depth limit is ignored and the node limit counts
+    /// playouts. With neither a node nor a time limit [DEFAULT_PLAYOUTS] are run. While
+    /// pondering the limits don't apply, they count from the ponder hit, and an infinite
+    /// search doesn't return before it's been stopped
+    pub fn search(&mut self, state: &mut State, limits: &SearchLimits) -> SearchResult {
+        let playouts = match limits {
+            SearchLimits {
+                nodes: None,
+                movetime: None,
+                infinite: false,
+                ..
+            } => Some(DEFAULT_PLAYOUTS),
+            _ => limits.nodes,
+        };
+        let out_of_time = |start: Instant| match limits.movetime {
+            Some(movetime) => start.elapsed() >= movetime,
+            None => false,
+        };
+        let out_of_playouts = |done: u64| match playouts {
+            Some(playouts) => done >= playouts,
+            None => false,
+        };
+
+        self.nodes.clear();
+        self.nodes.push(Node::new(None, 1.0));
+        let mut start = Instant::now();
+        let mut done = 0;
+        // the playouts since the ponder hit, which are the ones the limits count
+        let mut counted = 0;
+        while !limits.stopped() {
+            if limits.pondering() {
+                start = Instant::now();
+                counted = 0;
+            } else if out_of_time(start) || out_of_playouts(counted) {
+                break;
+            }
+            self.playout(state);
+            done += 1;
+            counted += 1;
+            // a root with no moves is all there is to know
+            if self.nodes[0].terminal.is_some() {
+                break;
+            }
+        }
+        // the best move mustn't be played before the opponent has moved
+        limits.wait();
+
+        let pv = self.principal_variation();
+        let score = match pv.first() {
+            Some(_) => self.score(self.best_child(0)),
+            None => 0,
+        };
+        SearchResult {
+            score,
+            lines: vec![Line {
+                score,
+                pv: pv.clone(),
+            }],
+            depth: pv.len() as i32,
+            pv,
+            nodes: done,
+        }
+    }
+
+    /// Walks down the tree to a leaf, expands and evaluates it, then updates the values on the way up
+    fn playout(&mut self, state: &mut State) {
+        let mut path = vec![0];
+        let mut node = 0;
+        while let Some((first, count)) = self.nodes[node].children
+            && path.len() < MAX_PLY as usize
+        {
+            node = self.select(node, first, count);
+            let (from, to) = self.nodes[node].m.expect("only the root has no move");
+            state.make_move(from, to);
+            path.push(node);
+        }
+
+        let value = match &self.nodes[node] {
+            Node {
+                terminal: Some(value),
+                ..
+            } => *value,
+            // too deep to go any further
+            Node {
+                children: Some(_), ..
+            } => self.evaluate(state),
+            _ => self.expand(state, node),
+        };
+
+        // the value is for the side to move at the leaf, nodes store it for the side that moved
+        let mut value = -value;
+        for &node in path.iter().rev() {
+            self.nodes[node].visits += 1;
+            self.nodes[node].value += value;
+            value = -value;
+        }
+        for _ in 1..path.len() {
+            state.unmake_move();
+        }
+    }
+
+    /// The child with the best mix of doing well and not having been looked at much
+    fn select(&self, parent: usize, first: usize, count: usize) -> usize {
+        let explore = self.options.exploration * (self.nodes[parent].visits as f32).sqrt();
+        let puct = |node: &Node| node.q() + explore * node.prior / (1 + node.visits) as f32;
+        (first..first + count)
+            .max_by(|&a, &b| puct(&self.nodes[a]).total_cmp(&puct(&self.nodes[b])))
+            .expect("expanded nodes have children")
+    }
+
+    /// Adds the children of a leaf, returning its value for the side to move
+    fn expand(&mut self, state: &mut State, node: usize) -> f32 {
+        // the root has to have moves to choose from, however it was reached
+        if node != 0 && state.is_repetition() {
+            self.nodes[node].terminal = Some(0.0);
+            return 0.0;
+        }
+
+        let moves = legal_moves(state);
+        if moves.is_empty() {
+            let value = match state.in_check(state.turn) {
+                true => -1.0,
+                false => 0.0,
+            };
+            self.nodes[node].terminal = Some(value);
+            return value;
+        }
+
+        // moves that leave the opponent worse off are more likely to be good
+        let scores = moves
+            .iter()
+            .map(|c| {
+                state.make_move(c.m.0, c.m.1);
+                let score = -self.evaluator.evaluate(state);
+                state.unmake_move();
+                score
+            })
+            .collect::<Vec<_>>();
+        let max = scores.iter().copied().max().unwrap_or(0);
+        let weights = scores
+            .iter()
+            .map(|&score| ((score - max) as f32 / PRIOR_TEMPERATURE).exp())
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<f32>();
+
+        let first = self.nodes.len();
+        for (c, weight) in moves.iter().zip(weights) {
+            self.nodes.push(Node::new(Some(c.m), weight / total));
+        }
+        self.nodes[node].children = Some((first, moves.len()));
+
+        self.evaluate(state)
+    }
+
+    /// The value of a position for the side to move, after a random rollout if there is one
+    fn evaluate(&mut self, state: &mut State) -> f32 {
+        let mut played = 0;
+        let mut sign = 1.0;
+        let mut value = None;
+        while played < self.options.rollout_depth {
+            let moves = legal_moves(state);
+            if moves.is_empty() {
+                value = Some(match state.in_check(state.turn) {
+                    true => -sign,
+                    false => 0.0,
+                });
+                break;
+            }
+            self.seed += 1;
+            let (from, to) = moves[(splitmix(self.seed) % moves.len() as u64) as usize].m;
+            state.make_move(from, to);
+            played += 1;
+            sign = -sign;
+        }
+
+        let value = value.unwrap_or_else(|| {
+            let score = quiescence(state, &mut self.evaluator, -MATE, MATE, &mut Vec::new());
+            sign * (score as f32 / VALUE_SCALE).tanh()
+        });
+        for _ in 0..played {
+            state.unmake_move();
+        }
+        value
+    }
+
+    /// The most visited child, which is the move to play
+    fn best_child(&self, node: usize) -> usize {
+        let (first, count) = self.nodes[node].children.unwrap_or((0, 0));
+        (first..first + count)
+            .max_by_key(|&child| self.nodes[child].visits)
+            .unwrap_or(node)
+    }
+
+    /// Follows the most visited moves for as long as they have been visited
+    fn principal_variation(&self) -> Vec<SquareMove> {
+        let mut pv = Vec::new();
+        let mut node = 0;
+        while self.nodes[node].children.is_some() {
+            node = self.best_child(node);
+            match self.nodes[node] {
+                Node { visits: 0, .. } | Node { m: None, .. } => break,
+                Node { m: Some(m), .. } => pv.push(m),
+            }
+        }
+        pv
+    }
+
+    /// The score of a node in centipawns, for the side that played the move
+    fn score(&self, node: usize) -> Score {
+        let node = &self.nodes[node];
+        match node.children {
+            // mating straight away
+            None if node.terminal == Some(-1.0) => MATE - 1,
+            _ => (node.q().clamp(-0.999, 0.999).atanh() * VALUE_SCALE) as Score,
+        }
+    }
+}
+
+impl Searcher for Mcts {
+    fn search(&mut self, state: &mut State, limits: &SearchLimits) -> SearchResult {
+        Mcts::search(self, state, limits)
+    }
+
+    fn clear(&mut self) {
+        Mcts::clear(self);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{chess::square::Square, rules::Rules, search::alphabeta::AlphaBeta};
+    use std::time::Duration;
+
+    fn state(fen: &str) -> State {
+        State::from_FEN(fen, Rules::standard()).unwrap()
+    }
+
+    #[test]
+    fn test_searchers() {
+        // the rook takes the undefended queen
+        let fen = "4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1";
+        let searchers: [Box<dyn Searcher>; 3] = [
+            Box::new(AlphaBeta::default()),
+            Box::new(Mcts::default()),
+            Box::new(Mcts::new(MctsOptions {
+                rollout_depth: 4,
+                ..Default::default()
+            })),
+        ];
+        for mut searcher in searchers {
+            let mut state = state(fen);
+            let result = searcher.search(&mut state, &SearchLimits::nodes(500));
+            assert_eq!(result.pv[0], (Square(11), Square(35)));
+            assert!(result.score > 300);
+            assert!(result.nodes <= 500);
+            assert!(state.history.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_ponder() {
+        // every searcher keeps going until the ponder hit, then stops at its limits
+        let fen = "4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1";
+        let searchers: [fn() -> Box<dyn Searcher>; 2] = [
+            || Box::new(AlphaBeta::default()),
+            || Box::new(Mcts::default()),
+        ];
+        for searcher in searchers {
+            let limits = SearchLimits::nodes(500).ponder();
+            let handle = {
+                let limits = limits.clone();
+                std::thread::spawn(move || searcher().search(&mut state(fen), &limits))
+            };
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!handle.is_finished());
+            limits.ponder_hit();
+            assert_eq!(handle.join().unwrap().pv[0], (Square(11), Square(35)));
+        }
+    }
+
+    #[test]
+    fn test_mcts_mate() {
+        // the queen mates on the back rank
+        let fen = "6k1/5ppp/8/8/8/8/8/3Q2K1 w - - 0 1";
+        let result = Mcts::default().search(&mut state(fen), &SearchLimits::nodes(1000));
+        assert_eq!(result.pv[0], (Square(3), Square(59)));
+        assert_eq!(result.score, MATE - 1);
+
+        // with no moves there is nothing to search
+        let fen = "k7/8/8/8/8/8/5q2/7K w - - 0 1";
+        let result = Mcts::default().search(&mut state(fen), &SearchLimits::nodes(1000));
+        assert!(result.pv.is_empty());
+        assert_eq!(result.nodes, 1);
+    }
+}
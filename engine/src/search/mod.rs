@@ -0,0 +1,114 @@
+pub mod alphabeta;
+pub mod bench;
+pub mod limits;
+pub mod mate;
+pub mod mcts;
+pub mod options;
+pub mod proof_number;
+pub mod quiescence;
+pub mod skill;
+pub mod tt;
+
+use std::cmp::Reverse;
+
+use crate::{
+    eval::Score,
+    move_gen::moves::Move,
+    rules::piece::PieceKind,
+    state::{board_state::GetPiece, State},
+};
+
+use self::{alphabeta::SearchResult, limits::SearchLimits};
+
+/// The score for capturing the king, anything close to it is a forced mate
+///
+/// Being mated `n` plies from the root scores `-MATE + n`
+pub const MATE: Score = 30_000;
+
+/// The deepest the search will ever go
+pub const MAX_PLY: i32 = 128;
+
+/// Something that picks a move, so different kinds of search can be swapped for each other
+pub trait Searcher {
+    /// Searches the position until a limit is reached, leaving it as it was
+    fn search(&mut self, state: &mut State, limits: &SearchLimits) -> SearchResult;
+
+    /// Forgets everything learnt from previous searches
+    fn clear(&mut self);
+}
+
+/// A score from the perspective of the side to move, with mates counted in moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eval {
+    Centipawns(Score),
+    /// Mate in this many moves, negative if the side to move is getting mated
+    Mate(i32),
+}
+
+impl Eval {
+    pub fn new(score: Score) -> Self {
+        let plies = MATE - score.abs();
+        if plies > MAX_PLY {
+            Eval::Centipawns(score)
+        } else if score > 0 {
+            Eval::Mate((plies + 1) / 2)
+        } else {
+            Eval::Mate(-(plies + 1) / 2)
+        }
+    }
+}
+
+impl std::fmt::Display for Eval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Eval::Centipawns(score) => write!(f, "{:+.2}", score as f32 / 100.0),
+            Eval::Mate(n) if n > 0 => write!(f, "mate in {n}"),
+            Eval::Mate(n) => write!(f, "mated in {}", -n),
+        }
+    }
+}
+
+/// The captures the side to move can make, most valuable victim first,
+/// then least valuable attacker first
+pub fn captures(state: &State) -> Vec<Move> {
+    let board = &state.board_state;
+    let value = |piece| board.get_info(piece).map_or(0, |info| info.value);
+
+    let mut captures = state
+        .moves
+        .iter()
+        .filter(|m| board.get_info(m.piece).map(|info| info.team) == Some(state.turn))
+        .filter(|m| board.get_info(m.to).is_some())
+        .copied()
+        .collect::<Vec<_>>();
+    captures.sort_by_key(|m| {
+        (
+            Reverse(value(m.to.get_piece(board))),
+            value(m.piece.get_piece(board)),
+            m.piece,
+            m.to,
+        )
+    });
+    captures
+}
+
+/// Returns true if this move takes a king
+pub fn takes_king(state: &State, m: Move) -> bool {
+    m.to.get_piece(&state.board_state).kind() == Some(PieceKind::King)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(Eval::new(34), Eval::Centipawns(34));
+        assert_eq!(Eval::new(MATE - 1), Eval::Mate(1));
+        assert_eq!(Eval::new(MATE - 5), Eval::Mate(3));
+        assert_eq!(Eval::new(-MATE + 4), Eval::Mate(-2));
+        assert_eq!(Eval::new(-34).to_string(), "-0.34");
+        assert_eq!(Eval::new(MATE - 3).to_string(), "mate in 2");
+        assert_eq!(Eval::new(-MATE).to_string(), "mated in 0");
+    }
+}
@@ -0,0 +1,60 @@
+use super::skill::Skill;
+
+/// Switches for the parts of the search that make it more selective
+///
+/// Everything is on by default, turning things off one at a time makes it easy to
+/// measure what each of them is worth
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Search one ply deeper when in check
+    pub check_extension: bool,
+    /// Search one ply deeper when the transposition table move is much better than the rest
+    pub singular_extension: bool,
+    /// Stop searching when the static evaluation is far enough above beta
+    pub reverse_futility: bool,
+    /// Skip quiet moves near the leaves when the static evaluation is far below alpha
+    pub futility: bool,
+    /// Drop straight into quiescence search when the static evaluation is hopeless
+    pub razoring: bool,
+    /// Skip late quiet moves near the leaves
+    pub late_move_pruning: bool,
+    /// How many threads to search with
+    pub threads: usize,
+    /// How many of the best moves to find lines for
+    pub multi_pv: usize,
+    /// How well to play, anything below full strength also limits the search
+    pub skill: Skill,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            check_extension: true,
+            singular_extension: true,
+            reverse_futility: true,
+            futility: true,
+            razoring: true,
+            late_move_pruning: true,
+            threads: 1,
+            multi_pv: 1,
+            skill: Skill::default(),
+        }
+    }
+}
+
+impl SearchOptions {
+    /// A plain alpha-beta search, with every extension and pruning turned off
+    pub fn none() -> Self {
+        Self {
+            check_extension: false,
+            singular_extension: false,
+            reverse_futility: false,
+            futility: false,
+            razoring: false,
+            late_move_pruning: false,
+            threads: 1,
+            multi_pv: 1,
+            skill: Skill::default(),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use rustc_hash::FxHashMap;
+
+use crate::state::State;
+
+use super::{
+    alphabeta::SquareMove,
+    mate::{legal_moves, Candidate},
+    MAX_PLY,
+};
+
+/// A proof or disproof number that can't be reached, the node is solved the other way
+const INFINITY: u64 = 1 << 48;
+
+/// What a proof-number search found out about the side to move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Proof {
+    /// The side to move can force mate
+    Proven,
+    /// The side to move can't force mate, however long it takes
+    Disproven,
+    /// The node budget ran out first
+    Unknown,
+}
+
+/// What a proof-number search found
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofResult {
+    pub proof: Proof,
+    /// The main line of the proof, ending in mate, empty unless it was proven
+    pub line: Vec<SquareMove>,
+    /// How many positions were expanded
+    pub nodes: u64,
+}
+
+/// A depth-first proof-number (df-pn) search for forced mates
+///
+/// Unlike alpha-beta it has no depth limit, it spends its time on the moves with the fewest
+/// replies, so it finds long forcing lines that a full width search never gets to
+///
+/// Repeating a position counts as failing to mate, and numbers stored for positions reached
+/// through a repetition aren't corrected later, so a disproof can very rarely be wrong
+#[derive(Default)]
+pub struct ProofNumberSearch {
+    /// The proof and disproof numbers of every position looked at so far
+    tt: FxHashMap<u64, (u64, u64)>,
+    /// The positions from the root to the one being searched
+    path: Vec<u64>,
+    nodes: u64,
+    budget: u64,
+}
+
+impl ProofNumberSearch {
+    /// Forgets everything from earlier searches
+    pub fn clear(&mut self) {
+        self.tt.clear();
+    }
+
+    /// Tries to prove that the side to move can force mate, expanding at most `budget` positions
+    pub fn search(&mut self, state: &mut State, budget: u64) -> ProofResult {
+        self.nodes = 0;
+        self.budget = budget;
+        self.path.clear();
+        self.path.push(state.hash);
+
+        let (pn, dn) = self.mid(state, true, INFINITY, INFINITY);
+        let proof = match (pn, dn) {
+            (0, _) => Proof::Proven,
+            (_, 0) => Proof::Disproven,
+            _ => Proof::Unknown,
+        };
+        let line = match proof {
+            Proof::Proven => self.main_line(state),
+            _ => Vec::new(),
+        };
+        ProofResult {
+            proof,
+            line,
+            nodes: self.nodes,
+        }
+    }
+
+    /// The numbers of a position reached from the one being searched
+    fn numbers(&self, hash: u64) -> (u64, u64) {
+        if self.path.contains(&hash) {
+            // going round in circles never mates anyone
+            return (INFINITY, 0);
+        }
+        self.tt.get(&hash).copied().unwrap_or((1, 1))
+    }
+
+    /// Combines the numbers of the children, the attacker needs one to work,
+    /// the defender needs one to escape
+    fn combine(&self, children: &[Candidate], attacker: bool) -> (u64, u64) {
+        let numbers = children.iter().map(|c| self.numbers(c.hash));
+        let (min, sum) = numbers.fold((INFINITY, 0), |(min, sum), (pn, dn)| match attacker {
+            true => (min.min(pn), (sum + dn).min(INFINITY)),
+            false => (min.min(dn), (sum + pn).min(INFINITY)),
+        });
+        match attacker {
+            true => (min, sum),
+            false => (sum, min),
+        }
+    }
+
+    /// Searches until the proof number reaches `pn_limit` or the disproof number reaches
+    /// `dn_limit`, returning the numbers of the position
+    fn mid(
+        &mut self,
+        state: &mut State,
+        attacker: bool,
+        pn_limit: u64,
+        dn_limit: u64,
+    ) -> (u64, u64) {
+        self.nodes += 1;
+        let mut children = legal_moves(state);
+        if children.is_empty() {
+            let numbers = match !attacker && state.in_check(state.turn) {
+                true => (0, INFINITY),
+                false => (INFINITY, 0),
+            };
+            self.tt.insert(state.hash, numbers);
+            return numbers;
+        }
+        if self.path.len() >= MAX_PLY as usize {
+            self.tt.insert(state.hash, (INFINITY, 0));
+            return (INFINITY, 0);
+        }
+        // checks first, they're the likeliest way to mate and the hardest to meet
+        children.sort_by_key(|c| (!c.check, !c.capture, c.m));
+
+        loop {
+            let (pn, dn) = self.combine(&children, attacker);
+            if pn >= pn_limit || dn >= dn_limit || self.nodes >= self.budget {
+                self.tt.insert(state.hash, (pn, dn));
+                return (pn, dn);
+            }
+
+            // the most promising child, and how good the next best one is
+            let key = |c: &Candidate| {
+                let (pn, dn) = self.numbers(c.hash);
+                match attacker {
+                    true => pn,
+                    false => dn,
+                }
+            };
+            let mut best = 0;
+            let mut second = INFINITY;
+            for (i, child) in children.iter().enumerate().skip(1) {
+                let value = key(child);
+                if value < key(&children[best]) {
+                    second = key(&children[best]);
+                    best = i;
+                } else {
+                    second = second.min(value);
+                }
+            }
+
+            // search the child until it stops being the best one
+            let (child_pn, child_dn) = self.numbers(children[best].hash);
+            let (pn_limit, dn_limit) = match attacker {
+                true => (
+                    pn_limit.min(second.saturating_add(1)),
+                    (dn_limit - dn).saturating_add(child_dn).min(INFINITY),
+                ),
+                false => (
+                    (pn_limit - pn).saturating_add(child_pn).min(INFINITY),
+                    dn_limit.min(second.saturating_add(1)),
+                ),
+            };
+            let (from, to) = children[best].m;
+            state.make_move(from, to);
+            self.path.push(state.hash);
+            self.mid(state, !attacker, pn_limit, dn_limit);
+            self.path.pop();
+            state.unmake_move();
+        }
+    }
+
+    /// Follows the proven moves from the root to the mate
+    fn main_line(&mut self, state: &mut State) -> Vec<SquareMove> {
+        self.path.clear();
+        let mut line = Vec::new();
+        while line.len() < MAX_PLY as usize {
+            self.path.push(state.hash);
+            let children = legal_moves(state);
+            let Some(proven) = children.iter().find(|c| self.numbers(c.hash).0 == 0) else { break };
+            let (from, to) = proven.m;
+            state.make_move(from, to);
+            line.push(proven.m);
+        }
+        for _ in &line {
+            state.unmake_move();
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rules;
+
+    fn state(fen: &str) -> State {
+        State::from_FEN(fen, Rules::standard()).unwrap()
+    }
+
+    #[test]
+    fn test_proof_number() {
+        // Philidor's legacy, mate in 4
+        let fen = "r6k/6pp/8/6N1/8/1Q6/8/6K1 w - - 0 1";
+        let mut state = state(fen);
+        let result = ProofNumberSearch::default().search(&mut state, 100_000);
+        assert_eq!(result.proof, Proof::Proven);
+        assert!(state.history.is_empty());
+
+        for &(from, to) in &result.line {
+            state.make_move(from, to);
+        }
+        assert!(legal_moves(&mut state).is_empty());
+        assert!(state.in_check(state.turn));
+
+        // the budget runs out long before the opening is solved
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let result = ProofNumberSearch::default().search(&mut self::state(fen), 100);
+        assert_eq!(result.proof, Proof::Unknown);
+        assert!(result.nodes <= 100);
+
+        // white is stalemated
+        let fen = "k7/8/8/8/8/8/5q2/7K w - - 0 1";
+        let result = ProofNumberSearch::default().search(&mut self::state(fen), 100);
+        assert_eq!(result.proof, Proof::Disproven);
+        assert!(result.line.is_empty());
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{
+    eval::{Evaluator, Score},
+    move_gen::moves::Move,
+    state::State,
+};
+
+use super::{captures, takes_king, MATE};
+
+/// Searches captures until the position is quiet, so the evaluation isn't
+/// fooled by a piece that is about to be taken
+///
+/// Returns the score from the perspective of the side to move,
+/// and fills `pv` with the captures that lead to it
+pub fn quiescence(
+    state: &mut State,
+    evaluator: &mut Evaluator,
+    mut alpha: Score,
+    beta: Score,
+    pv: &mut Vec<Move>,
+) -> Score {
+    pv.clear();
+    let captures = captures(state);
+    // the last move left a king hanging
+    if let Some(&first) = captures.first() && takes_king(state, first) {
+        return MATE;
+    }
+
+    let stand_pat = evaluator.evaluate(state);
+    if stand_pat >= beta {
+        return stand_pat;
+    }
+    alpha = alpha.max(stand_pat);
+
+    let mut line = Vec::new();
+    for m in captures {
+        state.make_move(state.board_state.square_of(m.piece), m.to);
+        let score = -quiescence(state, evaluator, -beta, -alpha, &mut line);
+        state.unmake_move();
+
+        if score >= beta {
+            return score;
+        }
+        if score > alpha {
+            alpha = score;
+            pv.clear();
+            pv.push(m);
+            pv.extend_from_slice(&line);
+        }
+    }
+
+    alpha
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Rules;
+
+    #[test]
+    fn test_quiescence() {
+        // the knight on d5 is hanging
+        let mut state =
+            State::from_FEN("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1", Rules::standard()).unwrap();
+        let mut pv = Vec::new();
+        let score = quiescence(&mut state, &mut Evaluator::default(), -MATE, MATE, &mut pv);
+
+        assert_eq!(pv.len(), 1);
+        assert_eq!(*pv[0].to, 35);
+        assert!(score > crate::eval::evaluate(&state));
+        assert!(state.history.is_empty());
+    }
+}
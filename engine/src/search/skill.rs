@@ -0,0 +1,179 @@
+use crate::{
+    eval::{Evaluator, Score},
+    state::{zobrist::splitmix, State},
+};
+
+use super::{alphabeta::Line, limits::SearchLimits, quiescence::quiescence, MATE};
+
+/// How strongly the engine plays, from 0 to [Skill::MAX_LEVEL]
+///
+/// Below full strength the search doesn't go as deep, looks at a few of the best moves instead
+/// of one, and then picks one of them a bit at random, sometimes without seeing the tactic
+/// that makes a move good
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Skill {
+    pub level: u8,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Self {
+            level: Self::MAX_LEVEL,
+        }
+    }
+}
+
+/// How many lines a weakened search chooses between
+pub const SKILL_MULTI_PV: usize = 4;
+/// How much better a line has to score than the position after its first move looks,
+/// for there to be a tactic in it that can be missed
+const TACTIC_MARGIN: Score = 150;
+/// The most the randomness can make up for, about a pawn
+const MAX_DELTA: Score = 100;
+
+impl Skill {
+    pub const MAX_LEVEL: u8 = 20;
+    /// The rough Elo of the weakest level, each level is [Skill::ELO_PER_LEVEL] stronger
+    pub const MIN_ELO: u32 = 1000;
+    pub const ELO_PER_LEVEL: u32 = 90;
+    pub const MAX_ELO: u32 = Self::MIN_ELO + Self::MAX_LEVEL as u32 * Self::ELO_PER_LEVEL;
+
+    pub fn new(level: u8) -> Self {
+        Self {
+            level: level.min(Self::MAX_LEVEL),
+        }
+    }
+
+    /// The level that plays closest to `elo`
+    pub fn from_elo(elo: u32) -> Self {
+        let elo = elo.clamp(Self::MIN_ELO, Self::MAX_ELO);
+        let level = (elo - Self::MIN_ELO + Self::ELO_PER_LEVEL / 2) / Self::ELO_PER_LEVEL;
+        Self::new(level as u8)
+    }
+
+    /// The rough Elo this level plays at
+    pub fn elo(&self) -> u32 {
+        Self::MIN_ELO + self.level as u32 * Self::ELO_PER_LEVEL
+    }
+
+    pub fn full_strength(&self) -> bool {
+        self.level >= Self::MAX_LEVEL
+    }
+
+    /// Makes the limits no more than this level is allowed to search
+    pub fn limit(&self, limits: &SearchLimits) -> SearchLimits {
+        if self.full_strength() {
+            return limits.clone();
+        }
+        let depth = 1 + self.level as i32 / 2;
+        let nodes = 1000 << (self.level / 2);
+        let mut limited = limits.clone();
+        limited.depth = Some(limits.depth.map_or(depth, |d| d.min(depth)));
+        limited.nodes = Some(limits.nodes.map_or(nodes, |n| n.min(nodes)));
+        limited
+    }
+
+    /// Picks one of the lines a weakened search found, best first, returning its index
+    ///
+    /// Worse lines get picked more often the lower the level, and a line whose point only
+    /// shows up a few moves in is sometimes judged by how it looks straight away instead
+    pub fn pick(&self, state: &mut State, lines: &[Line], seed: u64) -> usize {
+        let (Some(top), Some(bottom)) = (lines.first(), lines.last()) else { return 0 };
+        if self.full_strength() {
+            return 0;
+        }
+
+        let weakness = 120 - 2 * self.level as Score;
+        let delta = (top.score - bottom.score).min(MAX_DELTA);
+        let miss_chance =
+            (Self::MAX_LEVEL - self.level) as u64 * 100 / (2 * Self::MAX_LEVEL as u64);
+        let mut evaluator = Evaluator::default();
+
+        let mut best = 0;
+        let mut best_score = Score::MIN;
+        for (i, line) in lines.iter().enumerate() {
+            let random = splitmix(seed.wrapping_add(i as u64));
+            let mut score = line.score;
+
+            // how the move looks without thinking ahead
+            if let Some(&(from, to)) = line.pv.first() && random % 100 < miss_chance {
+                state.make_move(from, to);
+                let shallow = -quiescence(state, &mut evaluator, -MATE, MATE, &mut Vec::new());
+                state.unmake_move();
+                if score - shallow > TACTIC_MARGIN {
+                    score = shallow;
+                }
+            }
+
+            let noise = ((random >> 32) % weakness as u64) as Score;
+            let push = (weakness * (top.score - score) + delta * noise) / 128;
+            if score + push > best_score {
+                best = i;
+                best_score = score + push;
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        chess::square::Square,
+        rules::Rules,
+        search::{alphabeta::AlphaBeta, options::SearchOptions},
+    };
+
+    #[test]
+    fn test_elo() {
+        assert_eq!(Skill::from_elo(0), Skill::new(0));
+        assert_eq!(Skill::from_elo(Skill::MAX_ELO + 500), Skill::default());
+        for level in 0..=Skill::MAX_LEVEL {
+            assert_eq!(Skill::from_elo(Skill::new(level).elo()), Skill::new(level));
+        }
+        assert_eq!(Skill::new(50), Skill::default());
+    }
+
+    #[test]
+    fn test_skill() {
+        // the knight forks king and queen on c7
+        let fen = "q3k3/8/8/3N4/8/8/8/4K3 w - - 0 1";
+        let mut state = State::from_FEN(fen, Rules::standard()).unwrap();
+        let fork = (Square(35), Square(50));
+
+        let limits = Skill::new(0).limit(&SearchLimits::depth(8));
+        assert_eq!(limits.depth, Some(1));
+        assert_eq!(
+            Skill::default().limit(&SearchLimits::depth(8)).depth,
+            Some(8)
+        );
+
+        let lines = AlphaBeta::new(SearchOptions {
+            multi_pv: SKILL_MULTI_PV,
+            ..Default::default()
+        })
+        .search(&mut state, &SearchLimits::depth(4))
+        .lines;
+        assert_eq!(lines[0].pv[0], fork);
+
+        // full strength always plays the fork, the weakest level sometimes doesn't see it
+        let picks = (0..100)
+            .map(|seed| Skill::new(0).pick(&mut state, &lines, seed))
+            .collect::<Vec<_>>();
+        assert!((0..100).all(|seed| Skill::default().pick(&mut state, &lines, seed) == 0));
+        assert!(picks.contains(&0));
+        assert!(picks.iter().any(|&pick| pick != 0));
+        assert!(state.history.is_empty());
+
+        // the search itself is cut down too
+        let mut search = AlphaBeta::new(SearchOptions {
+            skill: Skill::new(0),
+            ..Default::default()
+        });
+        let result = search.search(&mut state, &SearchLimits::default());
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.lines.len(), 1);
+        assert_eq!(result.lines[0].pv, result.pv);
+    }
+}
@@ -0,0 +1,201 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{chess::square::Square, eval::Score};
+
+use super::MATE;
+
+/// What a stored score says about the real score
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Bound {
+    #[default]
+    Exact,
+    /// The real score is at least this, the search failed high
+    Lower,
+    /// The real score is at most this, the search failed low
+    Upper,
+}
+
+/// What we learnt from searching a position
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: i32,
+    pub score: Score,
+    pub bound: Bound,
+    /// The best move found, as the squares it goes from and to
+    pub best: Option<(Square, Square)>,
+}
+
+/// A hash table of positions that have already been searched, indexed by [State::hash](crate::state::State::hash)
+///
+/// Shared between search threads without any locks. Each slot stores the key xored with the
+/// data next to the data itself, so a slot torn by two threads writing at once just looks
+/// like a different position and is ignored
+pub struct TranspositionTable {
+    slots: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+    /// How many bytes each entry takes up
+    pub const ENTRY_SIZE: usize = std::mem::size_of::<[AtomicU64; 2]>();
+
+    /// Creates a table with `2^bits` entries
+    pub fn new(bits: u32) -> Self {
+        Self {
+            slots: (0..1 << bits)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+        }
+    }
+
+    fn slot(&self, key: u64) -> &[AtomicU64; 2] {
+        &self.slots[key as usize & (self.slots.len() - 1)]
+    }
+
+    fn load(&self, key: u64) -> Option<Entry> {
+        let [check, data] = self.slot(key);
+        let data = data.load(Ordering::Relaxed);
+        (check.load(Ordering::Relaxed) ^ data == key && data != 0).then(|| unpack(key, data))
+    }
+
+    /// Looks up a position, mate scores are adjusted to be relative to `ply`
+    pub fn probe(&self, key: u64, ply: i32) -> Option<Entry> {
+        self.load(key).map(|entry| Entry {
+            score: from_tt(entry.score, ply),
+            ..entry
+        })
+    }
+
+    /// Stores what was found for a position, replacing whatever was in its slot
+    /// unless that was searched deeper
+    pub fn store(
+        &self,
+        key: u64,
+        ply: i32,
+        depth: i32,
+        score: Score,
+        bound: Bound,
+        best: Option<(Square, Square)>,
+    ) {
+        let old = self.load(key);
+        if let Some(old) = old
+            && old.depth > depth
+            && bound != Bound::Exact
+        {
+            return;
+        }
+        let entry = Entry {
+            key,
+            depth,
+            score: to_tt(score, ply),
+            bound,
+            // keep the old move rather than forgetting it
+            best: best.or(old.and_then(|old| old.best)),
+        };
+
+        let data = pack(&entry);
+        let [check, slot] = self.slot(key);
+        check.store(key ^ data, Ordering::Relaxed);
+        slot.store(data, Ordering::Relaxed);
+    }
+
+    /// Forgets every position
+    pub fn clear(&self) {
+        for [check, data] in &self.slots {
+            check.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// How full the table is in permille, estimated from the first thousand slots
+    pub fn hashfull(&self) -> usize {
+        let sample = self.slots.len().min(1000);
+        let used = self.slots[..sample]
+            .iter()
+            .filter(|[_, data]| data.load(Ordering::Relaxed) != 0)
+            .count();
+        used * 1000 / sample
+    }
+}
+
+/// Packs everything but the key into 64 bits, never 0 so empty slots can be told apart
+///
+/// ```text
+/// | 16 score | 8 depth | 2 bound | 1 has move | 6 from | 6 to | 1 always set |
+/// ```
+fn pack(entry: &Entry) -> u64 {
+    let (has_move, from, to) = match entry.best {
+        Some((from, to)) => (1, *from as u64, *to as u64),
+        None => (0, 0, 0),
+    };
+    (entry.score as i16 as u16 as u64) << 48
+        | (entry.depth.clamp(0, 255) as u64) << 40
+        | (entry.bound as u64) << 38
+        | has_move << 37
+        | from << 31
+        | to << 25
+        | 1 << 24
+}
+
+fn unpack(key: u64, data: u64) -> Entry {
+    let bound = match data >> 38 & 3 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    let from = Square((data >> 31 & 63) as u8);
+    let to = Square((data >> 25 & 63) as u8);
+    let best = (data >> 37 & 1 == 1).then_some((from, to));
+    Entry {
+        key,
+        depth: (data >> 40 & 255) as i32,
+        score: (data >> 48) as u16 as i16 as Score,
+        bound,
+        best,
+    }
+}
+
+/// Mate scores count the distance from the root, stored ones count it from the position itself
+fn to_tt(score: Score, ply: i32) -> Score {
+    if score >= MATE - super::MAX_PLY {
+        score + ply
+    } else if score <= -MATE + super::MAX_PLY {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn from_tt(score: Score, ply: i32) -> Score {
+    if score >= MATE - super::MAX_PLY {
+        score - ply
+    } else if score <= -MATE + super::MAX_PLY {
+        score + ply
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_store() {
+        let tt = TranspositionTable::new(4);
+        let best = Some((Square(12), Square(28)));
+        tt.store(0xdead_beef, 0, 7, -123, Bound::Lower, best);
+
+        let entry = tt.probe(0xdead_beef, 0).unwrap();
+        assert_eq!(
+            (entry.depth, entry.score, entry.bound, entry.best),
+            (7, -123, Bound::Lower, best)
+        );
+        // same slot, different position
+        assert!(tt.probe(0xdead_beef + 16, 0).is_none());
+
+        // mates are stored relative to the position
+        tt.store(1, 3, 2, MATE - 5, Bound::Exact, None);
+        assert_eq!(tt.probe(1, 1).unwrap().score, MATE - 3);
+    }
+}
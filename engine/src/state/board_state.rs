@@ -17,20 +17,19 @@ use crate::{
 pub struct BoardState {
     board: Board<Index<Piece>>,
     pieces: [Piece; 64],
+    /// where each piece is, indexed the same way as `pieces`
+    squares: [Square; 64],
 }
 
-pub static mut PIECE_INFO: Vec<Option<PieceInfo>> = vec![];
-pub static mut PIECES: Vec<Box<dyn PieceTrait>> = vec![];
+/// Information about every piece, indexed by [Piece]
+#[ctor::ctor]
+pub static PIECE_INFO: Vec<Option<PieceInfo>> = Piece::iter().map(|p| p.info()).collect();
 
-/// initializes the piece info table
-pub fn init() {
-    unsafe {
-        PIECE_INFO = Piece::iter().map(|p| p.info()).collect();
-        PIECES = Piece::iter()
-            .map(|p| p.piece().unwrap_or(Box::new(Invalid)))
-            .collect();
-    }
-}
+/// The behaviour of every piece, indexed by [Piece]
+#[ctor::ctor]
+pub static PIECES: Vec<Box<dyn PieceTrait>> = Piece::iter()
+    .map(|p| p.piece().unwrap_or(Box::new(Invalid)))
+    .collect();
 
 pub trait GetPiece {
     fn get_piece(&self, state: &BoardState) -> Piece;
@@ -59,6 +58,7 @@ impl BoardState {
         Self {
             board: Board::new(),
             pieces: [Piece::Empty; 64],
+            squares: [Square(0); 64],
         }
     }
 
@@ -70,12 +70,17 @@ impl BoardState {
         &self.pieces
     }
 
+    /// Returns the square a piece is standing on
+    pub fn square_of(&self, idx: Index<Piece>) -> Square {
+        self.squares[idx.usize()]
+    }
+
     pub fn get_info<T: GetPiece>(&self, piece: T) -> Option<&PieceInfo> {
-        unsafe { PIECE_INFO[piece.get_piece(self) as usize].as_ref() }
+        PIECE_INFO[piece.get_piece(self) as usize].as_ref()
     }
 
     pub fn get_piece<T: GetPiece>(&self, piece: T) -> &'static dyn PieceTrait {
-        unsafe { PIECES[piece.get_piece(self) as usize].as_ref() }
+        PIECES[piece.get_piece(self) as usize].as_ref()
     }
 
     pub fn info_at(&self, pos: Square) -> Option<&PieceInfo> {
@@ -98,6 +103,7 @@ impl BoardState {
             .map(|(i, _)| i)
             .unwrap();
         self.pieces[i] = piece;
+        self.squares[i] = Square(pos.get() as u8);
         self.board[pos] = Index::new(i as u8);
     }
 
@@ -112,6 +118,17 @@ impl BoardState {
         self.board[pos] = Index::new(0);
     }
 
+    /// puts a removed piece back on the board under the index it had before
+    pub fn restore_piece<I: BoardIndex>(&mut self, idx: Index<Piece>, piece: Piece, pos: I) {
+        // assert that the position and the slot are both empty
+        debug_assert_eq!(self.board[pos], Index::new(0));
+        debug_assert_eq!(*idx.get(&self.pieces), Piece::Empty);
+
+        *idx.get_mut(&mut self.pieces) = piece;
+        self.squares[idx.usize()] = Square(pos.get() as u8);
+        self.board[pos] = idx;
+    }
+
     pub fn move_piece<I: BoardIndex>(&mut self, from: I, to: I) {
         // assert that the position is not empty
         debug_assert_ne!(self.board[from], Index::new(0));
@@ -124,5 +141,6 @@ impl BoardState {
         // move the piece
         self.board[to] = self.board[from];
         self.board[from] = Index::new(0);
+        self.squares[self.board[to].usize()] = Square(to.get() as u8);
     }
 }
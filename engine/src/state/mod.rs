@@ -1,5 +1,6 @@
 pub mod board_state;
 pub mod display;
 pub mod state;
+pub mod zobrist;
 
 pub use state::State;
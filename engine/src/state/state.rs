@@ -1,11 +1,24 @@
 use crate::{
     chess::{index::Index, square::Square, Team},
-    move_gen::moves::Moves,
-    rules::{piece_info::PieceInfo, Rules},
+    eval::{
+        self,
+        nnue::{Network, Nnue},
+        score::S,
+        Params,
+    },
+    move_gen::moves::{Move, Moves},
+    rules::{
+        piece::{Piece, PieceKind},
+        piece_info::PieceInfo,
+        Rules,
+    },
 };
 use std::sync::Arc;
 
-use super::board_state::BoardState;
+use super::{
+    board_state::BoardState,
+    zobrist::{self, ZOBRIST},
+};
 
 impl Index<PieceInfo> {
     pub fn is_empty(self) -> bool {
@@ -13,36 +26,216 @@ impl Index<PieceInfo> {
     }
 }
 
+/// Everything needed to take back a move
+#[derive(Clone, Debug)]
+pub struct Undo {
+    pub from: Square,
+    pub to: Square,
+    /// The piece that was captured, and the index it was stored under
+    pub captured: Option<(Index<Piece>, Piece)>,
+    /// The score before the move was made
+    pub score: S,
+    /// The pawn hash before the move was made
+    pub pawn_hash: u64,
+    /// The hash before the move was made
+    pub hash: u64,
+}
+
 /// A struct representing the state of a chess game
 #[derive(Clone, Debug)]
 pub struct State {
     /// The rules of the game
     pub rules: Arc<Rules>,
+    /// The weights used to evaluate the position
+    pub params: Arc<Params>,
     /// The team whose turn it is
     pub turn: Team,
     /// The state of the board
     pub board_state: BoardState,
     /// The list of moves that can be made
     pub moves: Moves,
+    /// Material and piece-square score from White's perspective, kept up to date as moves are made
+    pub score: S,
+    /// The phase of the game, see [eval::phase]
+    pub phase: i32,
+    /// Hash of just the pawns on the board, see [zobrist::pawn_hash]
+    pub pawn_hash: u64,
+    /// Hash of the whole position, see [zobrist::hash]
+    pub hash: u64,
+    /// The moves that have been made, most recent last
+    pub history: Vec<Undo>,
+    /// The neural network evaluation, used instead of the handcrafted one when set
+    pub nnue: Option<Nnue>,
 }
 
 impl State {
     pub fn new(rules: Rules) -> Self {
         Self {
             rules: Arc::new(rules),
+            params: Arc::new(Params::default()),
             turn: Team::White,
             board_state: BoardState::new(),
             moves: Moves::new(),
+            score: S::default(),
+            phase: 0,
+            pawn_hash: 0,
+            hash: 0,
+            history: Vec::new(),
+            nnue: None,
         }
     }
 
     /// Makes a move on the board
     pub fn make_move(&mut self, from: Square, to: Square) {
+        let piece = self.board_state.board()[from];
+        let target = self.board_state.board()[to];
+        let captured =
+            (target != Index::new(0)).then(|| (target, *target.get(self.board_state.pieces())));
+
+        self.history.push(Undo {
+            from,
+            to,
+            captured,
+            score: self.score,
+            pawn_hash: self.pawn_hash,
+            hash: self.hash,
+        });
+
+        // update the score with only the squares that changed
+        let moving = *piece.get(self.board_state.pieces());
+        self.score += self.params.piece_score(moving, to) - self.params.piece_score(moving, from);
+        let moved = ZOBRIST.piece(moving, from) ^ ZOBRIST.piece(moving, to);
+        self.hash ^= moved ^ ZOBRIST.turn;
+        if moving.kind() == Some(PieceKind::Pawn) {
+            self.pawn_hash ^= moved;
+        }
+        if let Some((_, captured)) = captured {
+            self.hash ^= ZOBRIST.piece(captured, to);
+            self.score -= self.params.piece_score(captured, to);
+            self.phase -= eval::piece_phase(captured);
+            if captured.kind() == Some(PieceKind::Pawn) {
+                self.pawn_hash ^= ZOBRIST.piece(captured, to);
+            }
+        }
+
         self.board_state.move_piece(from, to);
+        if let Some(nnue) = &mut self.nnue {
+            // empty squares aren't features, so they can stand in for no capture
+            let captured = captured.map_or(Piece::Empty, |(_, captured)| captured);
+            let removed = [(moving, from), (captured, to)];
+            nnue.make_move(&self.board_state, &removed, &[(moving, to)]);
+        }
         self.turn = self.turn.switch();
         self.moves = Moves::generate(&self.board_state);
         // dbg!(self);
     }
+
+    /// Takes back the last move made, returns false if there was nothing to take back
+    pub fn unmake_move(&mut self) -> bool {
+        let Some(undo) = self.history.pop() else {
+            return false;
+        };
+
+        self.board_state.move_piece(undo.to, undo.from);
+        if let Some((idx, piece)) = undo.captured {
+            self.board_state.restore_piece(idx, piece, undo.to);
+            self.phase += eval::piece_phase(piece);
+        }
+        if let Some(nnue) = &mut self.nnue {
+            nnue.unmake_move();
+        }
+        self.score = undo.score;
+        self.pawn_hash = undo.pawn_hash;
+        self.hash = undo.hash;
+        self.turn = self.turn.switch();
+        self.moves = Moves::generate(&self.board_state);
+        true
+    }
+
+    /// Sets the evaluation weights, rescoring the position with them
+    pub fn set_params(&mut self, params: Arc<Params>) {
+        self.params = params;
+        self.score = self.params.score(&self.board_state);
+    }
+
+    /// Where the king of `team` is
+    pub fn king(&self, team: Team) -> Option<Square> {
+        let king = PieceKind::King.piece(team);
+        let i = self.board_state.pieces().iter().position(|&p| p == king)?;
+        Some(self.board_state.square_of(Index::new(i as u8)))
+    }
+
+    /// Returns true if the king of `team` is attacked
+    pub fn in_check(&self, team: Team) -> bool {
+        match self.king(team) {
+            Some(king) => self.moves.is_attacked(king, team.switch()),
+            None => false,
+        }
+    }
+
+    /// Returns true if the side to move can move from `from` to `to` without leaving its king in check
+    pub fn is_legal(&mut self, from: Square, to: Square) -> bool {
+        let piece = self.board_state.board()[from];
+        let team = self.turn;
+        let pseudo_legal = self.board_state.get_info(piece).map(|info| info.team) == Some(team)
+            && self.moves.iter().any(|m| m.piece == piece && m.to == to);
+        if !pseudo_legal {
+            return false;
+        }
+        self.make_move(from, to);
+        let legal = !self.in_check(team);
+        self.unmake_move();
+        legal
+    }
+
+    /// The moves the side to move can make without leaving its king in check
+    pub fn legal_moves(&mut self) -> Vec<Move> {
+        let team = self.turn;
+        let board = &self.board_state;
+        let moves = self
+            .moves
+            .iter()
+            .filter(|m| board.get_info(m.piece).map(|info| info.team) == Some(team))
+            .map(|&m| (board.square_of(m.piece), m))
+            .collect::<Vec<_>>();
+
+        let mut legal = Vec::new();
+        for (from, m) in moves {
+            self.make_move(from, m.to);
+            if !self.in_check(team) {
+                legal.push(m);
+            }
+            self.unmake_move();
+        }
+        legal
+    }
+
+    /// Returns true if the position has been seen before, since the last capture or pawn move
+    pub fn is_repetition(&self) -> bool {
+        self.history
+            .iter()
+            .rev()
+            .take_while(|undo| undo.captured.is_none() && undo.pawn_hash == self.pawn_hash)
+            .skip(1)
+            .step_by(2)
+            .any(|undo| undo.hash == self.hash)
+    }
+
+    /// Switches to evaluating with a neural network, or back to the handcrafted evaluation with `None`
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.nnue = network.map(|network| Nnue::new(network, &self.board_state));
+    }
+
+    /// Recomputes the incrementally updated parts of the state from scratch
+    pub fn refresh(&mut self) {
+        self.score = self.params.score(&self.board_state);
+        self.phase = eval::phase(&self.board_state);
+        self.pawn_hash = zobrist::pawn_hash(&self.board_state);
+        self.hash = zobrist::hash(&self.board_state, self.turn);
+        if let Some(nnue) = &mut self.nnue {
+            nnue.refresh(&self.board_state);
+        }
+    }
 }
 
 pub trait StateGet<T> {
@@ -0,0 +1,74 @@
+use crate::{
+    chess::{square::Square, Team},
+    rules::piece::{Piece, PieceKind},
+};
+
+use super::board_state::BoardState;
+
+/// Random keys used to hash positions
+pub struct Zobrist {
+    /// One key for every piece on every square
+    pub pieces: [[u64; 64]; 14],
+    /// Mixed in when it's White's turn
+    pub turn: u64,
+}
+
+/// The `n`th output of splitmix64, good enough to spread the keys out
+pub(crate) const fn splitmix(n: u64) -> u64 {
+    let mut z = 0x0123_4567_89ab_cdef_u64.wrapping_add(n.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl Zobrist {
+    const fn new() -> Self {
+        let mut pieces = [[0; 64]; 14];
+        let mut p = 0;
+        while p < 14 {
+            let mut sq = 0;
+            while sq < 64 {
+                pieces[p][sq] = splitmix((p * 64 + sq + 1) as u64);
+                sq += 1;
+            }
+            p += 1;
+        }
+        Self {
+            pieces,
+            turn: splitmix(0),
+        }
+    }
+
+    /// The key for a piece standing on a square
+    #[inline(always)]
+    pub fn piece(&self, piece: Piece, square: Square) -> u64 {
+        match piece {
+            Piece::Empty | Piece::Captured => 0,
+            _ => self.pieces[piece as usize][*square as usize],
+        }
+    }
+}
+
+pub static ZOBRIST: Zobrist = Zobrist::new();
+
+/// Hashes the whole position
+pub fn hash(board: &BoardState, turn: Team) -> u64 {
+    let pieces = board.board().iter().enumerate().fold(0, |hash, (i, &idx)| {
+        hash ^ ZOBRIST.piece(*idx.get(board.pieces()), Square(i as u8))
+    });
+    match turn {
+        Team::White => pieces ^ ZOBRIST.turn,
+        Team::Black => pieces,
+    }
+}
+
+/// Hashes only the pawns on the board
+pub fn pawn_hash(board: &BoardState) -> u64 {
+    board
+        .board()
+        .iter()
+        .enumerate()
+        .map(|(i, &idx)| (i, *idx.get(board.pieces())))
+        .filter(|(_, p)| p.kind() == Some(PieceKind::Pawn))
+        .fold(0, |hash, (i, p)| hash ^ ZOBRIST.piece(p, Square(i as u8)))
+}
@@ -0,0 +1,221 @@
+//! Texel tuning, fitting the evaluation weights to the results of real games
+//!
+//! Every position is labelled with the result of the game it came from. The evaluation is
+//! turned into an expected result with a sigmoid, and the weights are nudged one at a time
+//! for as long as that lowers the mean squared error against the labels.
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    chess::Team,
+    eval::{self, Evaluator, Params, Score},
+    rules::Rules,
+    search::{quiescence::quiescence, MATE},
+    state::State,
+};
+
+/// A position along with how the game it came from ended
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub state: State,
+    /// 1.0 if White won, 0.5 for a draw and 0.0 if Black won
+    pub result: f64,
+}
+
+/// Parses a labelled position, returns `None` for blank lines and comments
+///
+/// The position is the first four fields of a FEN or EPD, the result can be given
+/// anywhere after it as `1-0`, `0-1` or `1/2-1/2` (for example in a `c9` opcode),
+/// or as `[1.0]`, `[0.5]` or `[0.0]`
+pub fn parse_line(line: &str) -> Result<Option<Entry>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    if fields.len() < 4 {
+        bail!("expected a position, found \"{line}\"");
+    }
+    let rest = fields[4..].join(" ");
+
+    let result = if let Some(start) = rest.find('[') {
+        let end = rest[start..].find(']').context("unclosed '['")? + start;
+        rest[start + 1..end]
+            .trim()
+            .parse::<f64>()
+            .with_context(|| format!("invalid result \"{}\"", &rest[start + 1..end]))?
+    } else {
+        let words = rest.split(|c: char| c.is_whitespace() || c == ';' || c == '"');
+        match words.filter_map(result_value).next() {
+            Some(result) => result,
+            None => bail!("no result found in \"{line}\""),
+        }
+    };
+
+    let fen = format!("{} 0 1", fields[..4].join(" "));
+    let state = State::from_FEN(&fen, Rules::standard())?;
+    Ok(Some(Entry { state, result }))
+}
+
+fn result_value(word: &str) -> Option<f64> {
+    match word {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" => Some(0.5),
+        _ => None,
+    }
+}
+
+/// Loads labelled positions from a file, one per line
+///
+/// Each position is replaced by the quiet position at the end of its quiescence search,
+/// so the evaluation isn't tuned on positions with captures hanging
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Entry>> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+
+    let mut evaluator = Evaluator::default();
+    let mut entries = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let entry = parse_line(line).with_context(|| format!("line {}", i + 1))?;
+        if let Some(mut entry) = entry {
+            resolve(&mut entry.state, &mut evaluator);
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Plays out the captures quiescence search expects, leaving a quiet position
+pub fn resolve(state: &mut State, evaluator: &mut Evaluator) {
+    let mut pv = Vec::new();
+    quiescence(state, evaluator, -MATE, MATE, &mut pv);
+    for m in pv {
+        state.make_move(state.board_state.square_of(m.piece), m.to);
+    }
+    state.history.clear();
+}
+
+/// Turns a score into an expected result, `k` scales how much a centipawn is worth
+pub fn sigmoid(score: Score, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score as f64 / 400.0))
+}
+
+/// The mean squared error between the results and what the evaluation expects them to be
+pub fn error(entries: &mut [Entry], params: &Arc<Params>, k: f64) -> f64 {
+    let total = entries
+        .iter_mut()
+        .map(|entry| {
+            entry.state.set_params(params.clone());
+            let score = match entry.state.turn {
+                Team::White => eval::evaluate(&entry.state),
+                Team::Black => -eval::evaluate(&entry.state),
+            };
+            (entry.result - sigmoid(score, k)).powi(2)
+        })
+        .sum::<f64>();
+    total / entries.len().max(1) as f64
+}
+
+/// Finds the scaling constant that fits the results best with the current weights
+pub fn fit_k(entries: &mut [Entry], params: &Arc<Params>) -> f64 {
+    // the error is smooth with one minimum, so a ternary search finds it
+    let (mut low, mut high) = (0.0, 10.0);
+    for _ in 0..50 {
+        let a = low + (high - low) / 3.0;
+        let b = high - (high - low) / 3.0;
+        if error(entries, params, a) < error(entries, params, b) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Tunes the weights with a local search, trying each weight one step up and one step down
+///
+/// Stops after `iterations` passes or when a whole pass doesn't improve anything.
+/// `progress` is called after every pass with its number, the error and the weights so far
+pub fn tune(
+    entries: &mut [Entry],
+    params: &Params,
+    k: f64,
+    iterations: usize,
+    mut progress: impl FnMut(usize, f64, &Params),
+) -> Params {
+    let mut weights = params.to_vec();
+    let mut best = error(entries, &Arc::new(params.clone()), k);
+
+    let with_weights = |weights: &[Score]| {
+        let mut params = params.clone();
+        params.set_from_slice(weights);
+        params
+    };
+
+    for iteration in 1..=iterations {
+        let mut improved = false;
+        for i in 0..weights.len() {
+            for step in [1, -1] {
+                weights[i] += step;
+                let candidate = error(entries, &Arc::new(with_weights(&weights)), k);
+                if candidate < best {
+                    best = candidate;
+                    improved = true;
+                    break;
+                }
+                weights[i] -= step;
+            }
+        }
+
+        progress(iteration, best, &with_weights(&weights));
+        if !improved {
+            break;
+        }
+    }
+
+    with_weights(&weights)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let entry = parse_line("4k3/8/8/8/8/8/4P3/4K3 w - - c9 \"1-0\";")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.result, 1.0);
+        let entry = parse_line("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1 [0.5]")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.result, 0.5);
+        assert_eq!(entry.state.turn, Team::Black);
+
+        assert!(parse_line("# comment").unwrap().is_none());
+        assert!(parse_line("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn test_tune() {
+        let mut entries = [
+            "4k3/8/8/8/8/8/4P3/4K3 w - - [1.0]",
+            "4k3/4p3/8/8/8/8/8/4K3 w - - [0.0]",
+            "4k3/4p3/8/8/8/8/4P3/4K3 w - - [0.5]",
+        ]
+        .map(|line| parse_line(line).unwrap().unwrap());
+        let params = Params::default();
+        let k = fit_k(&mut entries, &Arc::new(params.clone()));
+        assert!(k > 0.0);
+
+        let before = error(&mut entries, &Arc::new(params.clone()), k);
+        let tuned = tune(&mut entries, &params, k, 1, |_, _, _| ());
+        let after = error(&mut entries, &Arc::new(tuned), k);
+        assert!(after < before);
+    }
+}
@@ -7,7 +7,7 @@ pub struct PieceAssets(Handle<TextureAtlas>);
 
 impl PieceAssets {
     pub fn get_sprite(&self, piece: Piece) -> Option<SpriteSheetBundle> {
-        let piece = unsafe { PIECE_INFO.get(piece as usize)?.as_ref()? };
+        let piece = PIECE_INFO.get(piece as usize)?.as_ref()?;
         Some(SpriteSheetBundle {
             texture_atlas: self.0.clone(),
             sprite: TextureAtlasSprite {
@@ -1,4 +1,4 @@
-use engine::{chess::square::Square, rules::Rules, state::board_state::BoardState};
+use engine::{chess::square::Square, game::Game, state::board_state::BoardState};
 
 use crate::{assets::PieceAssets, theme::Theme, *};
 
@@ -8,14 +8,15 @@ pub const TILE_SPRITE_SIZE: Vec2 = Vec2::new(TILE_SIZE, TILE_SIZE + 2.0);
 #[derive(Resource)]
 pub struct Board {
     pub active: bool,
-    pub state: state::State,
+    /// The game being played, the board shows the position at its cursor
+    pub game: Game,
 }
 
 impl std::ops::Deref for Board {
     type Target = BoardState;
 
     fn deref(&self) -> &Self::Target {
-        &self.state.board_state
+        &self.game.state().board_state
     }
 }
 
@@ -92,11 +93,7 @@ pub fn init(mut commands: Commands) {
 
     commands.insert_resource(Board {
         active: true,
-        state: state::State::from_FEN(
-            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 ",
-            Rules::standard(),
-        )
-        .unwrap(),
+        game: Game::default(),
     });
     commands.init_resource::<Selectable>();
     // commands.init_resource::<Decorations>();
@@ -140,7 +137,7 @@ pub fn update_selectable(board: Res<Board>, mut selectable: ResMut<Selectable>)
             let get = || -> Option<bool> {
                 let team = board.get_info(piece)?.team;
 
-                Some(team == board.state.turn)
+                Some(team == board.game.state().turn)
             };
             selectable[i] = get().unwrap_or(false);
         }
@@ -169,7 +166,7 @@ pub fn spawn_board(
 ) {
     // debug
     if board.is_changed() {
-        let state = &board.state;
+        let state = board.game.state();
         println!("{state}");
     }
     // if the board is changed we reset it
@@ -227,7 +224,7 @@ pub fn spawn_board(
         }
     }
 
-    let board_state = &board.state.board_state;
+    let board_state = &board.game.state().board_state;
     for (i, piece) in board_state.board().iter().enumerate() {
         if let Some(mut sprite) = assets.get_sprite(*piece.get(board_state.pieces())) {
             sprite.sprite.color = theme.piece[board_state.get_info(*piece).unwrap().team as usize];
@@ -182,7 +182,7 @@ pub fn do_move_events(mut move_events: EventReader<MoveEvent>, mut board: ResMut
 
             if to_i.is_none() || to_i.is_some_and(|t| t.team != from_i.team) {
                 let piece = board.board()[from];
-                let moves = board.state.moves.filter(piece).collect::<Vec<_>>();
+                let moves = board.game.state().moves.filter(piece).collect::<Vec<_>>();
 
                 if moves.iter().any(|m| m.to == to) {
                     verified = true
@@ -190,8 +190,8 @@ pub fn do_move_events(mut move_events: EventReader<MoveEvent>, mut board: ResMut
             }
         }
 
-        if verified {
-            board.state.make_move(from, to);
+        if verified && let Err(e) = board.game.play((from, to)) {
+            error!("{e}");
         }
     }
 }
@@ -224,7 +224,7 @@ pub fn select(
                     return;
                 }
 
-                let moves = board.state.moves.filter(piece).collect::<Vec<_>>();
+                let moves = board.game.state().moves.filter(piece).collect::<Vec<_>>();
 
                 decorations.send(Decoration::Clear);
                 decorations.send(Decoration::Highlight(square));
@@ -255,7 +255,7 @@ pub fn select(
 
             if t.is_none() || t.is_some_and(|t| t.team != f.team) {
                 let piece = board.board()[from];
-                let moves = board.state.moves.filter(piece).collect::<Vec<_>>();
+                let moves = board.game.state().moves.filter(piece).collect::<Vec<_>>();
 
                 if moves.iter().any(|m| m.to == to) {
                     move_events.send(MoveEvent::new(from, to, true));
@@ -282,7 +282,7 @@ pub fn select(
             return;
         }
 
-        let moves = board.state.moves.filter(piece).collect::<Vec<_>>();
+        let moves = board.game.state().moves.filter(piece).collect::<Vec<_>>();
 
         decorations.send(Decoration::Clear);
         decorations.send(Decoration::Highlight(to));
@@ -11,11 +11,10 @@ mod misc;
 mod theme;
 
 use bevy::prelude::*;
-use engine::{rules, state};
+use engine::rules;
 use misc::EntityNamer;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    engine::init();
     std::env::set_current_dir(std::env::current_exe()?.parent().unwrap().parent().unwrap())?;
 
     // let rules = std::cell::RefCell::new(rules::Rules::standard());